@@ -13,15 +13,58 @@ pub enum Opcode {
     GreaterEqual,
     Not,
     Neg,
+    /// Prefix `++`, adding one to a variable and leaving the new value.
+    Increment,
+    /// Prefix `--`, subtracting one from a variable and leaving the new value.
+    Decrement,
     And,
     Or,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    /// Arithmetic shift, keeping the sign of the shifted value.
+    Shr,
     Assign,
 }
 
+/// Byte range of the source a node was parsed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// A node of the syntax tree together with the source it was parsed from.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
 #[derive(Debug, Clone)]
-pub enum Expr {
+pub enum ExprKind {
     Let(String, Box<Expr>),
     IfElse(Box<Expr>, Box<Expr>, Option<Box<Expr>>),
+    Loop(Box<Expr>),
+    While(Box<Expr>, Box<Expr>),
+    Break(Option<Box<Expr>>),
+    Continue,
+    Return(Option<Box<Expr>>),
+    /// Path of a file whose top-level bindings take the place of the import, see [`crate::import`].
+    Import(String),
     Constant(Atom),
     Block(Vec<Expr>),
     Call(Box<Expr>, Option<Box<Expr>>),
@@ -31,12 +74,16 @@ pub enum Expr {
     Object(Vec<(String, Expr)>),
 
     Index(Box<Expr>, Box<Expr>),
+    /// Copy of the elements of an array from a start index up to an end index, `a[start..end]`.
+    Slice(Box<Expr>, Box<Expr>, Box<Expr>),
     Member(Box<Expr>, String),
 
     // Unary
     UnaryOp(Opcode, Box<Expr>),
     // Binary
     BinaryOp(Box<Expr>, Opcode, Box<Expr>),
+    /// `target op= value`, like `a[i] += 1`, which evaluates the target once for both the read and the write.
+    CompoundAssign(Box<Expr>, Opcode, Box<Expr>),
 }
 
 #[derive(Debug, Clone)]
@@ -46,3 +93,97 @@ pub enum Atom {
     Identifier(String),
     String(String),
 }
+
+impl Opcode {
+    /// The operator as written in the source, `Neg` shares its `-` with `Sub`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Opcode::Add => "+",
+            Opcode::Sub | Opcode::Neg => "-",
+            Opcode::Mul => "*",
+            Opcode::Div => "/",
+            Opcode::Mod => "%",
+            Opcode::Equal => "==",
+            Opcode::NotEqual => "!=",
+            Opcode::Less => "<",
+            Opcode::LessEqual => "<=",
+            Opcode::Greater => ">",
+            Opcode::GreaterEqual => ">=",
+            Opcode::Not => "!",
+            Opcode::Increment => "++",
+            Opcode::Decrement => "--",
+            Opcode::And => "&&",
+            Opcode::Or => "||",
+            Opcode::BitAnd => "&",
+            Opcode::BitOr => "|",
+            Opcode::BitXor => "^",
+            Opcode::Shl => "<<",
+            Opcode::Shr => ">>",
+            Opcode::Assign => "=",
+        }
+    }
+}
+
+impl Expr {
+    /// Renders the tree with one node per line and its children indented below it.
+    ///
+    /// Unlike the `Debug` output the format is meant to be read and compared, it only changes with the AST itself.
+    pub fn pretty(&self) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, 0);
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, depth: usize) {
+        let line = |out: &mut String, depth: usize, text: String| {
+            out.push_str(&"  ".repeat(depth));
+            out.push_str(&text);
+            out.push('\n');
+        };
+        let (label, children): (String, Vec<&Expr>) = match &self.kind {
+            ExprKind::Let(name, value) => (format!("Let {}", name), vec![value]),
+            ExprKind::IfElse(cond, then, otherwise) => {
+                let mut children = vec![cond.as_ref(), then.as_ref()];
+                children.extend(otherwise.as_deref());
+                ("If".to_string(), children)
+            }
+            ExprKind::Loop(body) => ("Loop".to_string(), vec![body]),
+            ExprKind::While(cond, body) => ("While".to_string(), vec![cond, body]),
+            ExprKind::Break(value) => ("Break".to_string(), value.as_deref().into_iter().collect()),
+            ExprKind::Continue => ("Continue".to_string(), vec![]),
+            ExprKind::Return(value) => ("Return".to_string(), value.as_deref().into_iter().collect()),
+            ExprKind::Import(path) => (format!("Import {:?}", path), vec![]),
+            ExprKind::Constant(Atom::Num(value)) => (format!("Num {}", value), vec![]),
+            ExprKind::Constant(Atom::Boolean(value)) => (format!("Bool {}", value), vec![]),
+            ExprKind::Constant(Atom::Identifier(name)) => (format!("Identifier {}", name), vec![]),
+            ExprKind::Constant(Atom::String(value)) => (format!("String {:?}", value), vec![]),
+            ExprKind::Block(exprs) => ("Block".to_string(), exprs.iter().collect()),
+            ExprKind::Call(callee, arg) => {
+                let mut children = vec![callee.as_ref()];
+                children.extend(arg.as_deref());
+                ("Call".to_string(), children)
+            }
+            ExprKind::Lambda(Some(param), body) => (format!("Lambda {}", param), vec![body]),
+            ExprKind::Lambda(None, body) => ("Lambda".to_string(), vec![body]),
+            ExprKind::Array(items) => ("Array".to_string(), items.iter().collect()),
+            ExprKind::Object(fields) => {
+                line(out, depth, "Object".to_string());
+                for (key, value) in fields {
+                    line(out, depth + 1, format!("Field {}", key));
+                    value.write_tree(out, depth + 2);
+                }
+                return;
+            }
+            ExprKind::Index(target, index) => ("Index".to_string(), vec![target, index]),
+            ExprKind::Slice(target, start, end) => ("Slice".to_string(), vec![target, start, end]),
+            ExprKind::Member(target, name) => (format!("Member {}", name), vec![target]),
+            ExprKind::UnaryOp(op, operand) => (format!("Unary {}", op.symbol()), vec![operand]),
+            ExprKind::BinaryOp(lhs, op, rhs) => (format!("Binary {}", op.symbol()), vec![lhs, rhs]),
+            ExprKind::CompoundAssign(target, op, value) => (format!("Assign {}=", op.symbol()), vec![target, value]),
+        };
+        line(out, depth, label);
+        for child in children {
+            child.write_tree(out, depth + 1);
+        }
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use eyre::Result;
+use wasmtime::{Engine, Module};
+
+use crate::mir::ValueType;
+use crate::runtime::{HostState, Runtime};
+
+/// Modules compiled from sources by a hash of the source, so evaluating the same source again skips compiling it.
+///
+/// Sources are only ever compiled once, a cache is meant for a single set of compile options. All modules share one
+/// engine, a [`Module`] can be instantiated in as many runtimes as needed.
+pub struct ModuleCache {
+    engine: Engine,
+    modules: HashMap<u64, (Module, ValueType)>,
+    /// Number of sources compiled so far, the misses of the cache.
+    compiles: usize,
+}
+
+impl ModuleCache {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            engine: Runtime::engine()?,
+            modules: HashMap::new(),
+            compiles: 0,
+        })
+    }
+
+    /// Returns the module of the source and the type of its result, calling `compile` for the Wasm bytes of the
+    /// source the first time it is seen.
+    ///
+    /// Failures are not cached, a source that failed to compile is compiled again the next time.
+    pub fn get_or_compile(&mut self, source: &str, compile: impl FnOnce() -> Result<(Vec<u8>, ValueType)>) -> Result<(Module, ValueType)> {
+        let key = hash(source);
+        if let Some((module, result_type)) = self.modules.get(&key) {
+            return Ok((module.clone(), *result_type));
+        }
+        let (wasm, result_type) = compile()?;
+        self.compiles += 1;
+        let module = Module::new(&self.engine, wasm).map_err(|err| eyre::eyre!("Failed to create module: {:#?}", err))?;
+        self.modules.insert(key, (module.clone(), result_type));
+        Ok((module, result_type))
+    }
+
+    /// Creates a runtime for a module of this cache.
+    pub fn instantiate(&self, module: Module, state: HostState) -> Result<Runtime> {
+        Runtime::with_module(&self.engine, module, state)
+    }
+
+    pub fn compiles(&self) -> usize {
+        self.compiles
+    }
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
+
+fn hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+use crate::ast::Span;
+
+/// An error at a position in the source, displayed with the offending line and a `^` underline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceError {
+    /// 1-based line of the error.
+    pub line: usize,
+    /// 1-based column of the error, counted in characters.
+    pub col: usize,
+    pub message: String,
+    /// The whole source line containing the error.
+    pub source_line: String,
+    /// Number of characters underlined, at least one.
+    pub width: usize,
+}
+
+impl SourceError {
+    /// Locates the byte span `start..end` in the source, an empty span or one past the end underlines a single column.
+    pub fn new(source: &str, start: usize, end: usize, message: impl Into<String>) -> Self {
+        let start = start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+        let end = end.clamp(start, line_end);
+        Self {
+            line: source[..start].matches('\n').count() + 1,
+            col: source[line_start..start].chars().count() + 1,
+            message: message.into(),
+            source_line: source[line_start..line_end].trim_end_matches('\r').to_string(),
+            width: source[start..end].chars().count().max(1),
+        }
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let number = self.line.to_string();
+        let gutter = " ".repeat(number.len());
+        // Tabs are kept so the caret lines up however they are rendered
+        let padding: String = self.source_line.chars()
+            .take(self.col - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "line {}, column {}: {}", self.line, self.col, self.message)?;
+        writeln!(f, "{} | {}", number, self.source_line)?;
+        write!(f, "{} | {}{}", gutter, padding, "^".repeat(self.width))
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+/// An error at a span of the source found without the source at hand, like while compiling the AST.
+///
+/// [`locate`] turns it into a [`SourceError`] once the source is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl SpanError {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self { span, message: message.into() }
+    }
+}
+
+impl fmt::Display for SpanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at {}..{}", self.message, self.span.start, self.span.end)
+    }
+}
+
+impl std::error::Error for SpanError {}
+
+/// Replaces a [`SpanError`] by the [`SourceError`] it points at in the source, other errors are returned as they are.
+pub fn locate(err: eyre::Report, source: &str) -> eyre::Report {
+    match err.downcast_ref::<SpanError>() {
+        Some(error) => eyre::Report::new(SourceError::new(source, error.span.start, error.span.end, error.message.clone())),
+        None => err,
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use eyre::{Result, WrapErr};
+
+use crate::ast::{Expr, ExprKind, Span};
+use crate::parse_to_ast;
+
+/// Replaces every top-level `import "path"` of the program by the top-level `let` bindings of that file.
+///
+/// Paths are relative to `dir`, the directory of the importing source, and the imports of an imported file to its own
+/// directory. Importing a file that is still being imported fails instead of recursing forever.
+pub fn resolve_imports(ast: Expr, dir: &Path) -> Result<Expr> {
+    resolve(ast, dir, &mut Vec::new())
+}
+
+/// Resolves the imports of a program, `importing` holds the files whose imports are being resolved further out.
+fn resolve(ast: Expr, dir: &Path, importing: &mut Vec<PathBuf>) -> Result<Expr> {
+    let Expr { kind: ExprKind::Block(statements), span } = ast else {
+        return Ok(ast);
+    };
+    let mut resolved = Vec::with_capacity(statements.len());
+    for statement in statements {
+        let ExprKind::Import(path) = &statement.kind else {
+            resolved.push(statement);
+            continue;
+        };
+        let path = dir.join(path);
+        let canonical = path.canonicalize().wrap_err_with(|| format!("Failed to find imported file {}", path.display()))?;
+        if importing.contains(&canonical) {
+            eyre::bail!("Circular import of {}", path.display());
+        }
+        let source = std::fs::read_to_string(&canonical).wrap_err_with(|| format!("Failed to read imported file {}", path.display()))?;
+        let imported = parse_to_ast(&source).wrap_err_with(|| format!("Failed to parse imported file {}", path.display()))?;
+
+        importing.push(canonical.clone());
+        let imported = resolve(imported, canonical.parent().unwrap_or(dir), importing)?;
+        importing.pop();
+
+        let imported = match imported.kind {
+            ExprKind::Block(statements) => statements,
+            kind => vec![Expr::new(kind, imported.span)],
+        };
+        for mut binding in imported.into_iter().filter(|statement| matches!(statement.kind, ExprKind::Let(..))) {
+            respan(&mut binding, statement.span);
+            resolved.push(binding);
+        }
+    }
+    Ok(Expr::new(ExprKind::Block(resolved), span))
+}
+
+/// Points the node and everything below it at `span`.
+///
+/// Spans of imported nodes are into the imported file, so errors about them point at the import instead.
+fn respan(expr: &mut Expr, span: Span) {
+    expr.span = span;
+    match &mut expr.kind {
+        ExprKind::Let(_, inner) | ExprKind::Loop(inner) | ExprKind::Lambda(_, inner) | ExprKind::Member(inner, _) | ExprKind::UnaryOp(_, inner) => {
+            respan(inner, span);
+        }
+        ExprKind::IfElse(cond, then_expr, else_expr) => {
+            respan(cond, span);
+            respan(then_expr, span);
+            if let Some(else_expr) = else_expr {
+                respan(else_expr, span);
+            }
+        }
+        ExprKind::While(lhs, rhs) | ExprKind::Index(lhs, rhs) | ExprKind::BinaryOp(lhs, _, rhs) | ExprKind::CompoundAssign(lhs, _, rhs) => {
+            respan(lhs, span);
+            respan(rhs, span);
+        }
+        ExprKind::Break(value) | ExprKind::Return(value) => {
+            if let Some(value) = value {
+                respan(value, span);
+            }
+        }
+        ExprKind::Slice(array, start, end) => {
+            respan(array, span);
+            respan(start, span);
+            respan(end, span);
+        }
+        ExprKind::Call(callee, arg) => {
+            respan(callee, span);
+            if let Some(arg) = arg {
+                respan(arg, span);
+            }
+        }
+        ExprKind::Block(exprs) | ExprKind::Array(exprs) => exprs.iter_mut().for_each(|expr| respan(expr, span)),
+        ExprKind::Object(fields) => fields.iter_mut().for_each(|(_, expr)| respan(expr, span)),
+        ExprKind::Continue | ExprKind::Constant(_) | ExprKind::Import(_) => {}
+    }
+}
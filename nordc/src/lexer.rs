@@ -1,8 +1,23 @@
 use logos::Logos;
 use std::fmt;
+use std::num::IntErrorKind;
+
+/// Errors produced by the token callbacks, anything else is an invalid token.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub enum LexingError {
+    IntegerOverflow,
+    InvalidHexFloat,
+    InvalidEscape,
+    #[default]
+    InvalidToken,
+}
 
 #[derive(Logos, Clone, Debug, PartialEq)]
 #[logos(skip r"[ \t\n\f\r]+")]
+#[logos(skip r"//[^\n]*")]
+// Block comments do not nest, the first `*/` closes the comment
+#[logos(skip r"/\*[^*]*\*+([^/*][^*]*\*+)*/")]
+#[logos(error = LexingError)]
 pub enum Token {
     #[token("let")]
     KeywordLet,
@@ -22,10 +37,16 @@ pub enum Token {
     KeywordEnd,
     #[token("loop")]
     KeywordLoop,
+    #[token("while")]
+    KeywordWhile,
+    #[token("do")]
+    KeywordDo,
     #[token("break")]
     KeywordBreak,
     #[token("continue")]
     KeywordContinue,
+    #[token("import")]
+    KeywordImport,
 
     #[token("::")]
     DoubleColon,
@@ -52,11 +73,18 @@ pub enum Token {
 
     #[regex("[_a-zA-Z][_0-9a-zA-Z]*", |lex| lex.slice().parse().ok())]
     Identifier(String),
-    #[regex(r#"\d+"#, |lex| lex.slice().parse().ok())]
+    #[regex(r#"\d[\d_]*"#, |lex| parse_integer(lex.slice()))]
+    #[regex(r#"0[xX][0-9a-fA-F_]+"#, |lex| parse_integer(lex.slice()))]
+    #[regex(r#"0[oO][0-7_]+"#, |lex| parse_integer(lex.slice()))]
+    #[regex(r#"0[bB][01_]+"#, |lex| parse_integer(lex.slice()))]
     Integer(i64),
+    /// Hexadecimal float literal, only lexed: no grammar rule takes it, so a program using one is a syntax error.
+    #[regex(r#"0[xX][0-9a-fA-F]*\.[0-9a-fA-F]*([pP][+-]?[0-9]*)?"#, |lex| parse_hex_float(lex.slice()))]
+    #[regex(r#"0[xX][0-9a-fA-F]+[pP][+-]?[0-9]*"#, |lex| parse_hex_float(lex.slice()))]
+    Float(f64),
     #[regex("true|false", |lex| lex.slice().parse().ok())]
     Boolean(bool),
-    #[regex(r#""([^"\\]|\\.)*""#, |lex| lex.slice().parse().ok())]
+    #[regex(r#""([^"\\]|\\.)*""#, |lex| unescape_string(lex.slice()))]
     String(String),
 
     #[token("(")]
@@ -73,6 +101,16 @@ pub enum Token {
     RBracket,
     #[token("=")]
     Assign,
+    #[token("+=")]
+    AddAssign,
+    #[token("-=")]
+    SubAssign,
+    #[token("*=")]
+    MulAssign,
+    #[token("/=")]
+    DivAssign,
+    #[token("%=")]
+    ModAssign,
     #[token(";")]
     Semicolon,
 
@@ -80,6 +118,12 @@ pub enum Token {
     OperatorAdd,
     #[token("-")]
     OperatorSub,
+    /// Only in front of an operand, after one the lexer splits it into two `+`.
+    #[token("++")]
+    OperatorIncrement,
+    /// Only in front of an operand, after one the lexer splits it into two `-`.
+    #[token("--")]
+    OperatorDecrement,
     #[token("*")]
     OperatorMul,
     #[token("/")]
@@ -106,6 +150,98 @@ pub enum Token {
     OperatorAnd,
     #[token("||")]
     OperatorOr,
+
+    // Longest match keeps `&&` and `||` apart from their bitwise halves
+    #[token("&")]
+    OperatorBitAnd,
+    #[token("|")]
+    OperatorBitOr,
+    #[token("^")]
+    OperatorBitXor,
+    #[token("<<")]
+    OperatorShl,
+    #[token(">>")]
+    OperatorShr,
+}
+
+/// Parses a decimal, `0x` hexadecimal, `0o` octal or `0b` binary integer literal with `_` separators.
+fn parse_integer(slice: &str) -> Result<i64, LexingError> {
+    let digits = slice.replace('_', "");
+    let (digits, radix) = match digits.get(..2) {
+        Some("0x" | "0X") => (&digits[2..], 16),
+        Some("0o" | "0O") => (&digits[2..], 8),
+        Some("0b" | "0B") => (&digits[2..], 2),
+        _ => (&digits[..], 10),
+    };
+    i64::from_str_radix(digits, radix).map_err(|err| match err.kind() {
+        IntErrorKind::PosOverflow => LexingError::IntegerOverflow,
+        _ => LexingError::InvalidToken,
+    })
+}
+
+/// Parses a hexadecimal float literal like `0x1.8p3`, the binary exponent is required.
+fn parse_hex_float(slice: &str) -> Result<f64, LexingError> {
+    let digits = &slice[2..];
+    let (mantissa, exponent) = digits.split_once(['p', 'P']).ok_or(LexingError::InvalidHexFloat)?;
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(LexingError::InvalidHexFloat);
+    }
+    let exponent: i32 = exponent.parse().map_err(|_| LexingError::InvalidHexFloat)?;
+
+    let mut value = 0.0f64;
+    for digit in whole.chars().chain(fraction.chars()) {
+        let digit = digit.to_digit(16).ok_or(LexingError::InvalidHexFloat)?;
+        value = value * 16.0 + digit as f64;
+    }
+    let scale = exponent
+        .checked_sub(4 * fraction.len() as i32)
+        .ok_or(LexingError::InvalidHexFloat)?;
+    Ok(value * 2f64.powi(scale))
+}
+
+/// Strips the quotes off a string literal and decodes its escape sequences.
+fn unescape_string(slice: &str) -> Result<String, LexingError> {
+    let mut out = String::with_capacity(slice.len());
+    let mut chars = slice[1..slice.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('\\') => out.push('\\'),
+            Some('"') => out.push('"'),
+            _ => return Err(LexingError::InvalidEscape),
+        }
+    }
+    Ok(out)
+}
+
+impl Token {
+    /// The source text of a reserved keyword, `None` for every other token.
+    pub fn keyword(&self) -> Option<&'static str> {
+        match self {
+            Token::KeywordLet => Some("let"),
+            Token::KeywordFn => Some("fn"),
+            Token::KeywordIf => Some("if"),
+            Token::KeywordThen => Some("then"),
+            Token::KeywordElse => Some("else"),
+            Token::KeywordReturn => Some("return"),
+            Token::KeywordBlock => Some("block"),
+            Token::KeywordEnd => Some("end"),
+            Token::KeywordLoop => Some("loop"),
+            Token::KeywordWhile => Some("while"),
+            Token::KeywordDo => Some("do"),
+            Token::KeywordBreak => Some("break"),
+            Token::KeywordContinue => Some("continue"),
+            Token::KeywordImport => Some("import"),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Token {
@@ -1,24 +1,57 @@
+use std::fmt;
+
 use logos::{Logos, SpannedIter};
 
-use crate::lexer::Token;
+use crate::lexer::{LexingError, Token};
 
 pub type Spanned<Tok, Loc, Error> = Result<(Loc, Tok, Loc), Error>;
 
+/// Error of the lexing stage, raised before the parser sees the offending token.
 #[derive(Debug)]
 pub enum LexicalError {
     InvalidToken((usize, String, usize)),
+    IntegerOverflow((usize, String, usize)),
+    InvalidHexFloat((usize, String, usize)),
+    InvalidEscape((usize, String, usize)),
+}
+
+impl fmt::Display for LexicalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexicalError::InvalidToken((start, slice, _)) => match slice.chars().next() {
+                Some(c) => write!(f, "unexpected character '{}' at byte {}", c, start),
+                None => write!(f, "unexpected end of input at byte {}", start),
+            },
+            LexicalError::IntegerOverflow((start, slice, end)) => write!(f, "integer literal too large for i64: `{}` at {}..{}", slice, start, end),
+            LexicalError::InvalidHexFloat((start, slice, end)) => write!(f, "malformed hex float literal `{}` at {}..{}", slice, start, end),
+            LexicalError::InvalidEscape((start, slice, end)) => write!(f, "invalid escape sequence in string literal `{}` at {}..{}", slice, start, end),
+        }
+    }
 }
 
+impl std::error::Error for LexicalError {}
+
 pub struct Lexer<'input> {
     // instead of an iterator over characters, we have a token iterator
     token_stream: SpannedIter<'input, Token>,
+    /// Whether the last token ends an operand, which makes a following `++` or `--` two binary and unary signs.
+    after_operand: bool,
+    /// Second half of a split `++` or `--`.
+    pending: Option<(usize, Token, usize)>,
 }
 
 impl<'input> Lexer<'input> {
     pub fn new(input: &'input str) -> Self {
         // the Token::lexer() method is provided by the Logos trait
+        let mut lexer = Token::lexer(input);
+        if input.starts_with("#!") {
+            // Skip a shebang on the first line, bumping past it keeps the spans of the rest intact
+            lexer.bump(input.find('\n').unwrap_or(input.len()));
+        }
         Self {
-            token_stream: Token::lexer(input).spanned(),
+            token_stream: lexer.spanned(),
+            after_operand: false,
+            pending: None,
         }
     }
 }
@@ -27,14 +60,51 @@ impl<'input> Iterator for Lexer<'input> {
     type Item = Spanned<Token, usize, LexicalError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.token_stream.next().map(|(token, span)| match token {
+        if let Some(token) = self.pending.take() {
+            return Some(Ok(token));
+        }
+        let token = self.token_stream.next().map(|(token, span)| match token {
             Ok(token) => Ok((span.start, token, span.end)),
-            Err(_) => {
+            Err(err) => {
                 let start = span.start;
                 let end = span.end;
-                let slice = &self.token_stream.source()[start..end];
-                Err(LexicalError::InvalidToken((start, slice.to_string(), end)))
+                let slice = self.token_stream.source()[start..end].to_string();
+                match err {
+                    LexingError::IntegerOverflow => Err(LexicalError::IntegerOverflow((start, slice, end))),
+                    LexingError::InvalidHexFloat => Err(LexicalError::InvalidHexFloat((start, slice, end))),
+                    LexingError::InvalidEscape => Err(LexicalError::InvalidEscape((start, slice, end))),
+                    LexingError::InvalidToken => Err(LexicalError::InvalidToken((start, slice, end))),
+                }
+            }
+        });
+        // `++` and `--` only increment and decrement in front of an operand, `5--3` is still `5 - -3`
+        let token = match token {
+            Some(Ok((start, Token::OperatorIncrement, end))) if self.after_operand => {
+                self.pending = Some((start + 1, Token::OperatorAdd, end));
+                Some(Ok((start, Token::OperatorAdd, start + 1)))
+            }
+            Some(Ok((start, Token::OperatorDecrement, end))) if self.after_operand => {
+                self.pending = Some((start + 1, Token::OperatorSub, end));
+                Some(Ok((start, Token::OperatorSub, start + 1)))
             }
-        })
+            token => token,
+        };
+        self.after_operand = matches!(
+            token,
+            Some(Ok((
+                _,
+                Token::Identifier(_)
+                    | Token::Integer(_)
+                    | Token::Float(_)
+                    | Token::Boolean(_)
+                    | Token::String(_)
+                    | Token::RParen
+                    | Token::RBracket
+                    | Token::RBrace
+                    | Token::KeywordEnd,
+                _
+            )))
+        );
+        token
     }
 }
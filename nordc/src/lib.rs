@@ -0,0 +1,163 @@
+pub mod ast;
+pub mod cache;
+pub mod diagnostic;
+pub mod import;
+mod lexer_ext;
+pub mod lexer;
+pub mod mir;
+pub mod optimize;
+pub mod runtime;
+pub mod stats;
+mod mir_context;
+mod mir_var_context;
+
+use std::path::Path;
+
+use eyre::{Result, WrapErr};
+use lalrpop_util::{lalrpop_mod, ParseError};
+
+use crate::ast::{Atom, Expr, ExprKind};
+use crate::diagnostic::SourceError;
+use crate::lexer::Token;
+use crate::mir::{Mir, WasmOptions};
+
+pub use crate::lexer_ext::LexicalError;
+
+lalrpop_mod!(pub parser); // synthesized by LALRPOP
+
+/// Lexes the input into its token stream.
+pub fn lex_to_tokens(input: &str) -> Result<Vec<Token>> {
+    lexer_ext::Lexer::new(input)
+        .map(|token| token
+            .map(|(_, token, _)| token)
+            .map_err(eyre::Report::new))
+        .collect()
+}
+
+/// Lexes the input into its tokens, each with the byte offsets it starts and ends at.
+pub fn lex_to_spanned_tokens(input: &str) -> Result<Vec<(usize, Token, usize)>> {
+    lexer_ext::Lexer::new(input)
+        .map(|token| token.map_err(eyre::Report::new))
+        .collect()
+}
+
+/// Parses the input into an AST, syntax errors are reported as a [`SourceError`].
+pub fn parse_to_ast(input: &str) -> Result<Expr> {
+    let lexer = lexer_ext::Lexer::new(input);
+    let parser = parser::ProgramParser::new();
+    parser.parse(lexer).map_err(|err| {
+        let (start, end, message) = match err {
+            ParseError::UnrecognizedToken { token: (start, token, end), expected }
+                if token.keyword().is_some() && expected.iter().any(|expected| expected == "\"identifier\"") =>
+            {
+                let keyword = token.keyword().unwrap_or_default();
+                (start, end, format!("`{}` is a reserved keyword and cannot be used as an identifier", keyword))
+            }
+            ParseError::UnrecognizedToken { token: (start, Token::Float(_), end), .. } => {
+                (start, end, format!("float literal `{}` is not supported, programs only compute with integers", &input[start..end]))
+            }
+            ParseError::UnrecognizedToken { token: (start, _, end), expected } => {
+                (start, end, format!("unexpected `{}`{}", &input[start..end], describe_expected(&expected)))
+            }
+            ParseError::UnrecognizedEof { location, expected } => {
+                (location, location, format!("unexpected end of input{}", describe_expected(&expected)))
+            }
+            ParseError::ExtraToken { token: (start, _, end) } => (start, end, format!("unexpected `{}`", &input[start..end])),
+            ParseError::InvalidToken { location } => (location, location, "invalid token".to_string()),
+            // Lexing failed before the parser got to the input, kept as its own error type
+            ParseError::User { error } => return eyre::Report::new(error),
+        };
+        eyre::Report::new(SourceError::new(input, start, end, message))
+    })
+}
+
+/// Lists the terminals the parser expected, as a suffix for an error message.
+fn describe_expected(expected: &[String]) -> String {
+    let terminals: Vec<String> = expected.iter().map(|terminal| format!("`{}`", terminal.trim_matches('"'))).collect();
+    match terminals.as_slice() {
+        [] => String::new(),
+        [terminal] => format!(", expected {}", terminal),
+        terminals => format!(", expected one of {}", terminals.join(", ")),
+    }
+}
+
+/// Returns the terminals the parser would accept after the input, for completion.
+pub fn expected_tokens(input: &str) -> Result<Vec<String>> {
+    // `...` is not part of the grammar, so appending it always fails right after the input
+    let end = input.len();
+    let lexer = lexer_ext::Lexer::new(input).chain(std::iter::once(Ok((end, Token::DotDotDot, end))));
+    let parser = parser::ProgramParser::new();
+    match parser.parse(lexer) {
+        Err(ParseError::UnrecognizedToken { expected, .. }) | Err(ParseError::UnrecognizedEof { expected, .. }) => Ok(expected),
+        Err(ParseError::User { error }) => Err(eyre::Report::new(error)),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Rewrites a program so every top-level statement before the last prints its value as it runs.
+///
+/// A binding prints the bound value, statements without a value print nothing. The last statement is left to be
+/// the program's result, a final binding makes the program evaluate to the bound value.
+pub fn echo_statements(ast: Expr) -> Expr {
+    let Expr { kind: ExprKind::Block(statements), span } = ast else {
+        return ast;
+    };
+    // The inserted nodes take the span of the statement they echo
+    let print = |expr: Expr| {
+        let span = expr.span;
+        let callee = Expr::new(ExprKind::Constant(Atom::Identifier("print".to_string())), span);
+        Expr::new(ExprKind::Call(Box::new(callee), Some(Box::new(expr))), span)
+    };
+    let last = statements.len().saturating_sub(1);
+    let mut echoed = Vec::new();
+    for (i, statement) in statements.into_iter().enumerate() {
+        match &statement.kind {
+            ExprKind::Let(name, _) => {
+                let name = Expr::new(ExprKind::Constant(Atom::Identifier(name.clone())), statement.span);
+                echoed.push(statement);
+                echoed.push(if i == last { name } else { print(name) });
+            }
+            _ if i < last && mir::produces_value(&statement) => echoed.push(print(statement)),
+            _ => echoed.push(statement),
+        }
+    }
+    Expr::new(ExprKind::Block(echoed), span)
+}
+
+/// Compiles an AST into MIR instructions.
+pub fn compile_to_mir(ast: &Expr) -> Result<Vec<Mir>> {
+    mir::compile(ast)
+}
+
+/// Lowers MIR instructions into the bytes of a Wasm module.
+pub fn mir_to_wasm_bytes(bytecode: &[Mir], options: WasmOptions) -> Result<Vec<u8>> {
+    mir::to_wasm_module(bytecode, options)
+}
+
+/// Compiles source code into the bytes of a Wasm module exporting `main`, ready for `runtime::Runtime`.
+///
+/// Like syntax errors, compile errors pointing into the program are reported as a [`SourceError`]. Imports are
+/// relative to the current directory.
+pub fn compile_source(input: &str) -> Result<Vec<u8>> {
+    compile_source_in(input, Path::new("."))
+}
+
+/// Compiles a source file like [`compile_source`], with its imports relative to the file's directory.
+pub fn compile_file(path: &Path) -> Result<Vec<u8>> {
+    let input = std::fs::read_to_string(path).wrap_err_with(|| format!("Failed to read {}", path.display()))?;
+    compile_source_in(&input, path.parent().unwrap_or(Path::new(".")))
+}
+
+fn compile_source_in(input: &str, dir: &Path) -> Result<Vec<u8>> {
+    let ast = import::resolve_imports(parse_to_ast(input)?, dir)?;
+    let bytecode = compile_to_mir(&ast).map_err(|err| diagnostic::locate(err, input))?;
+    mir_to_wasm_bytes(&bytecode, WasmOptions::for_program(&ast))
+}
+
+/// Compiles and runs source code with the given arguments, returning the result of `main` together with everything it printed.
+pub fn run_source_capturing(input: &str, args: &[i64]) -> Result<(i64, String)> {
+    let wasm = compile_source(input)?;
+    let mut runtime = runtime::Runtime::new_capturing(&wasm)?;
+    let result = runtime.run(args)?;
+    Ok((result, runtime.take_output()))
+}
@@ -1,24 +1,22 @@
 #![feature(try_blocks)]
 
-pub mod ast;
-mod lexer_ext;
-pub mod lexer;
-pub mod mir;
-mod runtime;
-mod mir_context;
-mod mir_var_context;
-
-use std::io::Write;
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 use color_eyre::eyre::Result;
 use clap::Parser;
 use eyre::WrapErr;
-use lalrpop_util::lalrpop_mod;
-use logos::Logos;
-use wasmtime::{Config, Engine};
-use reedline::{DefaultPrompt, DefaultPromptSegment, EditCommand, Emacs, Keybindings, KeyCode, KeyModifiers, Reedline, ReedlineEvent, Signal};
-
-lalrpop_mod!(pub parser); // synthesized by LALRPOP
+use reedline::{DefaultPrompt, DefaultPromptSegment, Reedline, Signal};
+use nordc::{compile_to_mir, echo_statements, lex_to_spanned_tokens, lex_to_tokens, mir_to_wasm_bytes, parse_to_ast};
+use nordc::mir::{self, Overflow, ValueType, WasmOptions};
+use nordc::runtime::{HostState, Runtime};
+use nordc::cache::ModuleCache;
+use nordc::optimize::optimize;
+use nordc::stats;
+use nordc::diagnostic;
+use nordc::import::resolve_imports;
+use nordc::ast::{Atom, Expr, ExprKind, Opcode, Span};
 
 /// Your App's CLI options.
 #[derive(Parser, Debug)]
@@ -28,58 +26,216 @@ struct Cli {
     #[clap(short = 'i', long)]
     input: Option<String>,
 
-    /// Executes the script provided directly as an argument.
+    /// Executes the script provided directly as an argument, repeated fragments run as one program.
     #[clap(short = 'e', long)]
-    execute: Option<String>,
+    execute: Vec<String>,
 
     /// Silent mode: Only print out the result.
     #[clap(short = 's', long)]
     silent: bool,
 
-    /// Executes from stdin and outputs to stdout.
+    /// Increases the pipeline detail printed: `-v` for AST, MIR and WAT, `-vv` to add tokens.
+    #[clap(short = 'v', long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Executes every line of stdin as its own program, printing one bare result per line and errors to stderr.
+    ///
+    /// Implies silent mode so the output can be piped.
     #[clap(short = 'd', long)]
     std: bool,
+
+    /// Runs the script given with -e or -i and prints nothing but its bare result, like `42` or `true`.
+    ///
+    /// Implies silent mode so the output can be used by other programs.
+    #[clap(long)]
+    run: bool,
+
+    /// Prints every result as plain text in the interactive mode, instead of `Ok("...")`.
+    #[clap(long)]
+    result_as_string: bool,
+
+    /// Traps when a local variable is read before it was assigned, instead of reading 0.
+    #[clap(long)]
+    strict_locals: bool,
+
+    /// Aborts the program once its loops ran this many iterations in total.
+    #[clap(long, value_name = "N")]
+    max_steps: Option<u64>,
+
+    /// What `+`, `-` and `*` do when the result does not fit in an i64: `wrap`, `checked` or `saturate`.
+    #[clap(long, value_name = "MODE", default_value = "wrap")]
+    overflow: Overflow,
+
+    /// Aborts the program once it used up this much wasmtime fuel, roughly one unit per instruction.
+    #[clap(long, value_name = "N")]
+    fuel: Option<u64>,
+
+    /// Aborts the program once it ran for this many milliseconds.
+    #[clap(long, value_name = "MS")]
+    timeout: Option<u64>,
+
+    /// Optimizes the compiled module with wasm-opt at this level, from 0 (off) to 4.
+    #[clap(long = "opt", value_name = "LEVEL", default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=4))]
+    opt_level: u8,
+
+    /// Writes the compiled Wasm module to the given path instead of running it.
+    #[clap(long, value_name = "PATH")]
+    emit: Option<String>,
+
+    /// Writes the compiled module in the WebAssembly text format to the given path instead of running it.
+    #[clap(long, value_name = "PATH")]
+    emit_wat: Option<String>,
+
+    /// Passes an integer argument to a program that is a lambda, once per parameter.
+    #[clap(long = "arg", value_name = "INT", allow_hyphen_values = true)]
+    args: Vec<i64>,
+
+    /// Prints the value of every top-level statement of a script, not just the last one.
+    #[clap(long)]
+    echo: bool,
+
+    /// Prints the function, local, instruction and byte counts of the compiled module to stderr.
+    #[clap(long)]
+    stats: bool,
+
+    /// Prints how long every stage of the pipeline took to stderr, from lexing to running the module.
+    ///
+    /// Constant programs are evaluated without a module, which is reported as the `evaluating` stage.
+    #[clap(long)]
+    time: bool,
+
+    /// Prints the tokens of the script with their byte offsets instead of running it.
+    #[clap(long)]
+    tokens: bool,
+
+    /// Prints the syntax tree of the script instead of running it.
+    #[clap(long)]
+    ast: bool,
+}
+
+/// How programs are compiled and run, shared by every mode.
+#[derive(Debug, Clone, Default)]
+struct RunOptions {
+    wasm: WasmOptions,
+    /// Arguments passed to a program that is a lambda.
+    args: Vec<i64>,
+    /// Print the statistics of every compiled module.
+    stats: bool,
+    /// Print the value of every top-level statement of scripts.
+    echo: bool,
+    /// wasm-opt level applied to every compiled module, 0 leaves it as emitted.
+    opt_level: u8,
+    /// Keep reports like the optimized sizes quiet.
+    silent: bool,
+    /// Fuel budget of every run, unlimited when not set.
+    fuel: Option<u64>,
+    /// Wall-clock limit of every run, unlimited when not set.
+    timeout: Option<Duration>,
+    /// Print the duration of every stage of the pipeline.
+    time: bool,
 }
 
 fn main() -> Result<()> {
     color_eyre::install()?;
     let cli = Cli::parse();
+    // Streaming and scripted runs only ever write results to stdout
+    let silent = cli.silent || cli.std || cli.run;
+    init_logger(if silent { 0 } else { cli.verbose });
+    let options = RunOptions {
+        wasm: WasmOptions {
+            strict_locals: cli.strict_locals,
+            max_steps: cli.max_steps,
+            overflow: cli.overflow,
+            ..WasmOptions::default()
+        },
+        args: cli.args.clone(),
+        stats: cli.stats,
+        echo: cli.echo,
+        opt_level: cli.opt_level,
+        silent,
+        fuel: cli.fuel,
+        timeout: cli.timeout.map(Duration::from_millis),
+        time: cli.time,
+    };
+
+    if cli.tokens {
+        let sources = match (&cli.execute[..], &cli.input) {
+            ([], Some(script_path)) => vec![std::fs::read_to_string(script_path)?],
+            ([], None) => eyre::bail!("Printing tokens requires a script given with -e or -i"),
+            (fragments, _) => fragments.to_vec(),
+        };
+        for source in &sources {
+            for (start, token, end) in lex_to_spanned_tokens(source)? {
+                println!("{}..{} {:?}", start, end, token);
+            }
+        }
+        return Ok(());
+    }
+
+    if cli.ast {
+        let ast = parse_script(&cli, "Printing the syntax tree")?;
+        print!("{}", ast.unwrap_or_else(empty_program).pretty());
+        return Ok(());
+    }
+
+    if cli.emit.is_some() || cli.emit_wat.is_some() {
+        let ast = parse_script(&cli, "Emitting a module")?;
+        return emit(ast, &options, cli.emit.as_deref(), cli.emit_wat.as_deref());
+    }
+
+    if cli.run && cli.execute.is_empty() && cli.input.is_none() {
+        eyre::bail!("--run requires a script given with -e or -i");
+    }
 
     if cli.std {
+        let mut stdout = std::io::stdout();
+        let mut cache = ModuleCache::new()?;
         loop {
-            // Read a line from stdin
+            // Read a line from stdin, stopping at its end
             let mut input = String::new();
-            let result: Result<String> = try {
-                std::io::stdin().read_line(&mut input)?;
-                if input.trim().is_empty() {
-                    continue;
-                }
-                let output = execute(&input, cli.silent)?;
-                output
-            };
-            println!("{:?}", result);
+            if std::io::stdin().read_line(&mut input).wrap_err("Failed to read from stdin")? == 0 {
+                break;
+            }
+            if input.trim().is_empty() {
+                continue;
+            }
+            match execute_cached(&input, &options, &mut cache) {
+                Ok(output) => writeln!(stdout, "{}", output)?,
+                Err(err) => eprintln!("{:?}", err),
+            }
+            // Whoever reads the pipe sees every result as soon as it is ready
+            stdout.flush()?;
         }
-    } else if let Some(script) = cli.execute {
-        let output = execute(&script, cli.silent)?;
+    } else if !cli.execute.is_empty() {
+        let output = execute_fragments(&cli.execute, &options)?;
         println!("{}", output);
     } else if let Some(script_path) = cli.input {
-        let input = std::fs::read_to_string(script_path)?;
-        let output = execute(&input, cli.silent)?;
+        let input = std::fs::read_to_string(&script_path)?;
+        let output = execute(&input, script_dir(&script_path), &options)?;
         println!("{}", output);
+    } else if !std::io::stdin().is_terminal() {
+        // Piped into the interactive mode, every line runs in one session like it was typed at the prompt
+        let mut session = Session::new()?;
+        for line in std::io::stdin().lines() {
+            let line = line.wrap_err("Failed to read from stdin")?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            print_result(&session.execute(&line, &options), cli.result_as_string);
+        }
     } else {
         // Interactive mode: read from stdin
         let mut line_editor = Reedline::create();
 
         let prompt = DefaultPrompt::new(DefaultPromptSegment::Basic("nord".to_string()), DefaultPromptSegment::Empty);
+        let mut session = Session::new()?;
 
         loop {
             let result: Result<String> = try {
                 let signal = line_editor.read_line(&prompt);
                 match signal {
-                    Ok(Signal::Success(buffer)) => {
-                        let output = execute(&buffer, cli.silent)?;
-                        output
-                    }
+                    Ok(Signal::Success(buffer)) if buffer.trim().is_empty() => continue,
+                    Ok(Signal::Success(buffer)) => session.execute(&buffer, &options)?,
                     Ok(Signal::CtrlD) | Ok(Signal::CtrlC) => {
                         break;
                     }
@@ -88,67 +244,376 @@ fn main() -> Result<()> {
                     }
                 }
             };
-            if cli.silent == false {
-                println!("===== Output:");
-                println!("{:?}", result);
-                println!();
-            }
+            log::info!("===== Output:");
+            print_result(&result, cli.result_as_string);
         }
     }
     Ok(())
 }
 
+/// Sets up the logger printing pipeline diagnostics to stderr for the given verbosity level.
+fn init_logger(verbosity: u8) {
+    let level = match verbosity {
+        0 => log::LevelFilter::Warn,
+        1 => log::LevelFilter::Info,
+        _ => log::LevelFilter::Debug,
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .format(|buf, record| writeln!(buf, "{}", record.args()))
+        .init();
+}
+
+/// Prints the outcome of a line, a successful result as bare text when `as_string` is set.
+fn print_result(result: &Result<String>, as_string: bool) {
+    match result {
+        Ok(output) if as_string => println!("{}", output),
+        _ => println!("{:?}", result),
+    }
+}
+
+/// The program of a script without any statements.
+fn empty_program() -> Expr {
+    Expr::new(ExprKind::Block(vec![]), Span::default())
+}
+
+/// Result printed for programs that produce no value.
+const UNIT: &str = "()";
+
 /// Executes the script, lexing, parsing, and interpreting the input.
-fn execute(
-    input: &str,
-    silent: bool,
-) -> Result<String> {
-    if silent == false {
-        // Lex
-        let lexer = lexer::Token::lexer(&input);
-        print!("===== Tokens:\n");
-        for token in lexer {
-            println!("{:?}", token);
+fn execute(input: &str, dir: &Path, options: &RunOptions) -> Result<String> {
+    match parse(input, dir, options.time)? {
+        Some(ast) => run_script(ast, options).map_err(|err| diagnostic::locate(err, input)),
+        None => Ok(UNIT.to_string()),
+    }
+}
+
+/// Executes the fragments of repeated `-e` flags as one program sharing its bindings.
+fn execute_fragments(fragments: &[String], options: &RunOptions) -> Result<String> {
+    execute(&join_fragments(fragments), Path::new("."), options)
+}
+
+/// Joins the fragments of repeated `-e` flags into one source, each on its own line.
+///
+/// Statements are separated by semicolons rather than newlines, so one is added after every fragment but the last
+/// that holds something and does not end in one already.
+fn join_fragments(fragments: &[String]) -> String {
+    let mut source = String::new();
+    for (i, fragment) in fragments.iter().enumerate() {
+        if i > 0 {
+            source.push('\n');
         }
-        println!();
+        source.push_str(fragment);
+        let fragment = fragment.trim_end();
+        if i + 1 < fragments.len() && !fragment.is_empty() && !fragment.ends_with(';') {
+            source.push(';');
+        }
+    }
+    source
+}
+
+/// Parses the script given with `-i` or `-e` for the modes that do not run it, naming the mode when there is none.
+fn parse_script(cli: &Cli, mode: &str) -> Result<Option<Expr>> {
+    match (&cli.execute[..], &cli.input) {
+        ([], Some(script_path)) => parse(&std::fs::read_to_string(script_path)?, script_dir(script_path), cli.time),
+        ([], None) => eyre::bail!("{} requires a script given with -e or -i", mode),
+        (fragments, _) => parse(&join_fragments(fragments), Path::new("."), cli.time),
+    }
+}
+
+/// Directory the imports of a script file are relative to.
+fn script_dir(script_path: &str) -> &Path {
+    Path::new(script_path).parent().unwrap_or(Path::new("."))
+}
+
+/// Lexes and parses the input and resolves its imports against `dir`, returning `None` when it holds no tokens at all.
+fn parse(input: &str, dir: &Path, time: bool) -> Result<Option<Expr>> {
+    // Lex
+    let tokens = timed(time, "lexing", || lex_to_tokens(input))?;
+    if tokens.is_empty() {
+        // Nothing but whitespace and comments, there is no program to run
+        return Ok(None);
+    }
+    if log::log_enabled!(log::Level::Debug) {
+        log::debug!("===== Tokens:");
+        for token in tokens {
+            log::debug!("{:?}", token);
+        }
+        log::debug!("");
     }
 
     // Parse
-    let lexer = lexer_ext::Lexer::new(&input);
-    let parser = parser::ExprParser::new();
-    let output = parser.parse(lexer);
-    match &output {
-        Ok(output) => {
-            if silent == false {
-                println!("===== AST:\n{:#?}", output);
-                println!();
+    let ast = timed(time, "parsing", || resolve_imports(parse_to_ast(input)?, dir))?;
+    log::info!("===== AST:\n{}", ast.pretty());
+    Ok(Some(ast))
+}
+
+/// Executes the input like [`execute`], reusing the module of an identical input executed before.
+fn execute_cached(input: &str, options: &RunOptions, cache: &mut ModuleCache) -> Result<String> {
+    let (module, result_type) = cache
+        .get_or_compile(input, || {
+            let ast = parse(input, Path::new("."), options.time)?.unwrap_or_else(empty_program);
+            let ast = if options.echo { echo_statements(ast) } else { ast };
+            compile(&ast, options)
+        })
+        .map_err(|err| diagnostic::locate(err, input))?;
+    let runtime = timed(options.time, "instantiating", || cache.instantiate(module, HostState::default()))?;
+    run_module(runtime, result_type, options)
+}
+
+/// Runs a whole script, echoing its statements when asked to, a constant one without compiling it.
+fn run_script(ast: Expr, options: &RunOptions) -> Result<String> {
+    if let Some(output) = constant_result(&ast, options) {
+        return Ok(output);
+    }
+    let ast = if options.echo { echo_statements(ast) } else { ast };
+    run(&ast, options)
+}
+
+/// The result of a program made of a single constant integer expression, which needs no module to be run.
+///
+/// Programs are always compiled when the module is reported on or given arguments, a constant one takes none. The
+/// attempt is timed as a stage of its own, so `--time` accounts for constant programs too.
+fn constant_result(ast: &Expr, options: &RunOptions) -> Option<String> {
+    if options.stats || options.echo || options.opt_level > 0 || !options.args.is_empty() {
+        return None;
+    }
+    timed(options.time, "evaluating", || mir::const_eval(ast)).map(|value| value.to_string())
+}
+
+/// Compiles the AST to Wasm and runs it.
+fn run(ast: &Expr, run_options: &RunOptions) -> Result<String> {
+    let (wasm, result_type) = compile(ast, run_options)?;
+    let runtime = timed(run_options.time, "instantiating", || Runtime::new(&wasm))?;
+    run_module(runtime, result_type, run_options)
+}
+
+/// Compiles the AST to a Wasm module, returned with the type of its result.
+fn compile(ast: &Expr, run_options: &RunOptions) -> Result<(Vec<u8>, ValueType)> {
+    // Get the bytecode
+    let (bytecode, result_type) = timed(run_options.time, "compiling", || mir::compile_typed(ast))?;
+    let options = run_options.wasm.with_params_of(ast);
+    log::info!("===== Bytecode:\n{}", mir::to_pseudo_wat(&bytecode));
+
+    // Compile to Wasm
+    let wasm = timed(run_options.time, "lowering", || mir_to_wasm_bytes(&bytecode, options)).wrap_err_with(|| "Failed to compile to Wasm")?;
+    let wasm = optimize_module(wasm, run_options)?;
+    if log::log_enabled!(log::Level::Info) {
+        log::info!("===== Wasm: {} bytes", wasm.len());
+        // Printed from the returned bytes, so the log shows the module after optimization
+        log::info!("{}", wasmprinter::print_bytes(&wasm).map_err(|err| eyre::eyre!("Failed to print Wasm: {:#?}", err))?);
+    }
+    if run_options.stats {
+        eprintln!("{}", stats::module_stats(&wasm)?);
+    }
+    Ok((wasm, result_type))
+}
+
+/// Runs the instantiated module, formatting what it returns as a value of `result_type`.
+fn run_module(mut runtime: Runtime, result_type: ValueType, run_options: &RunOptions) -> Result<String> {
+    let result = run_main(&mut runtime, run_options)?;
+    format_result(&mut runtime, result, result_type)
+}
+
+/// Calls `main` of the instantiated module with the fuel and time it is given.
+fn run_main(runtime: &mut Runtime, run_options: &RunOptions) -> Result<i64> {
+    let call = |runtime: &mut Runtime| match run_options.fuel {
+        Some(fuel) => runtime.run_with_fuel(&run_options.args, fuel),
+        None => runtime.run(&run_options.args),
+    };
+    timed(run_options.time, "running", || match run_options.timeout {
+        Some(timeout) => runtime.with_timeout(timeout, call),
+        None => call(runtime),
+    })
+}
+
+/// Formats an i64 returned by the module as a value of `result_type`, reading arrays from its memory.
+fn format_result(runtime: &mut Runtime, result: i64, result_type: ValueType) -> Result<String> {
+    if result_type == ValueType::Array {
+        let elements: Vec<String> = runtime.read_array(result)?.iter().map(i64::to_string).collect();
+        return Ok(format!("[{}]", elements.join(", ")));
+    }
+    Ok(result_type.format(result))
+}
+
+/// Runs one stage of the pipeline, printing how long it took to stderr when `time` is set.
+fn timed<T>(time: bool, stage: &str, f: impl FnOnce() -> T) -> T {
+    if !time {
+        return f();
+    }
+    let start = Instant::now();
+    let value = f();
+    eprintln!("{}: {:?}", stage, start.elapsed());
+    value
+}
+
+/// Optimizes the module at the requested level, reporting the size it went from and to unless silent.
+fn optimize_module(wasm: Vec<u8>, run_options: &RunOptions) -> Result<Vec<u8>> {
+    if run_options.opt_level == 0 {
+        return Ok(wasm);
+    }
+    let optimized = timed(run_options.time, "optimizing", || optimize(&wasm, run_options.opt_level))?;
+    if !run_options.silent {
+        eprintln!("Optimized at level {}: {} -> {} bytes", run_options.opt_level, wasm.len(), optimized.len());
+    }
+    Ok(optimized)
+}
+
+/// Compiles the script and writes the module to the requested paths without running it.
+fn emit(ast: Option<Expr>, run_options: &RunOptions, wasm_path: Option<&str>, wat_path: Option<&str>) -> Result<()> {
+    let ast = ast.unwrap_or_else(empty_program);
+    let bytecode = compile_to_mir(&ast)?;
+    let options = run_options.wasm.with_params_of(&ast);
+    if let Some(path) = wasm_path {
+        let wasm = mir_to_wasm_bytes(&bytecode, options)?;
+        let wasm = optimize_module(wasm, run_options)?;
+        if run_options.stats {
+            eprintln!("{}", stats::module_stats(&wasm)?);
+        }
+        std::fs::write(path, wasm).wrap_err_with(|| format!("Failed to write Wasm module to {}", path))?;
+    }
+    if let Some(path) = wat_path {
+        let wat = mir::to_wat_text(&bytecode, options)?;
+        std::fs::write(path, wat).wrap_err_with(|| format!("Failed to write WAT module to {}", path))?;
+    }
+    Ok(())
+}
+
+/// Interactive session state, every line is compiled after bindings of the values its variables were left with by the
+/// previous lines.
+///
+/// Only the values are carried over, so the statements of earlier lines never run again. An array is bound to a literal
+/// of its elements, an element holding a nested array is only the address it had in the module of its line.
+struct Session {
+    /// Name and value of every variable of the lines so far, in the order they were first bound.
+    bindings: Vec<(String, Expr)>,
+    cache: ModuleCache,
+}
+
+/// Span of the nodes a session adds around a line, which are not part of its source.
+const REPLAYED: Span = Span { start: usize::MAX, end: usize::MAX };
+
+/// Name of the binding holding the result of a line while the variables are captured, no identifier can spell it.
+const RESULT: &str = "%result";
+
+impl Session {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            bindings: Vec::new(),
+            cache: ModuleCache::new()?,
+        })
+    }
+
+    /// Executes a line after the bindings so far, keeping the values it leaves only if it runs to the end.
+    fn execute(&mut self, input: &str, options: &RunOptions) -> Result<String> {
+        let locate = |err| locate_line(err, input);
+        let statements = match parse(input, Path::new("."), options.time).map_err(locate)? {
+            Some(Expr { kind: ExprKind::Block(statements), .. }) => statements,
+            Some(ast) => vec![ast],
+            None => vec![],
+        };
+        let mut program: Vec<Expr> = self.bindings.iter()
+            .map(|(name, value)| Expr::new(ExprKind::Let(name.clone(), Box::new(value.clone())), REPLAYED))
+            .collect();
+        program.extend(statements.iter().cloned());
+        if let Some(Expr { kind: ExprKind::Let(name, _), span }) = statements.last() {
+            // A binding line evaluates to the bound value
+            program.push(identifier(name, *span));
+        }
+        let block = |statements: Vec<Expr>| Expr::new(ExprKind::Block(statements), REPLAYED);
+        let (_, result_type) = mir::compile_typed(&block(program.clone())).map_err(locate)?;
+
+        // A line that may return or is a function leaves no variables to capture, its program is run as it is
+        let returns = statements.iter().any(|statement| any_node(statement, &|expr| matches!(expr.kind, ExprKind::Return(_))));
+        if returns || matches!(&statements[..], [Expr { kind: ExprKind::Lambda(..), .. }]) {
+            let program = block(program);
+            let (module, result_type) = self.cache
+                .get_or_compile(&format!("{:?}", program), || compile(&program, options))
+                .map_err(locate)?;
+            let runtime = timed(options.time, "instantiating", || self.cache.instantiate(module, HostState::default()))?;
+            return run_module(runtime, result_type, options);
+        }
+
+        // The line ends in an array of its result and the value of every variable, unit ones have none to carry
+        let mut names: Vec<String> = self.bindings.iter().map(|(name, _)| name.clone()).collect();
+        for statement in &statements {
+            if let ExprKind::Let(name, _) = &statement.kind {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        let mut variables = Vec::new();
+        for name in names {
+            let mut probe = program.clone();
+            probe.push(identifier(&name, REPLAYED));
+            let (_, value_type) = mir::compile_typed(&block(probe)).map_err(locate)?;
+            if value_type != ValueType::Unit {
+                variables.push((name, value_type));
             }
         }
-        Err(err) => {
-            return Err(eyre::eyre!("AST Error: {:#?}", err));
+        let mut captured: Vec<Expr> = variables.iter().map(|(name, _)| identifier(name, REPLAYED)).collect();
+        if result_type != ValueType::Unit {
+            let result = program.pop().expect("A line with a result has a statement");
+            program.push(Expr::new(ExprKind::Let(RESULT.to_string(), Box::new(result)), REPLAYED));
+            captured.insert(0, identifier(RESULT, REPLAYED));
         }
+        program.push(Expr::new(ExprKind::Array(captured), REPLAYED));
+        let program = block(program);
+        // The whole program is the key, a line imports files that may have changed since it was last entered
+        let (module, _) = self.cache
+            .get_or_compile(&format!("{:?}", program), || compile(&program, options))
+            .map_err(locate)?;
+        let mut runtime = timed(options.time, "instantiating", || self.cache.instantiate(module, HostState::default()))?;
+        let address = run_main(&mut runtime, options)?;
+        let mut values = runtime.read_array(address)?.into_iter();
+        let output = match result_type {
+            ValueType::Unit => UNIT.to_string(),
+            _ => format_result(&mut runtime, values.next().expect("The result is captured first"), result_type)?,
+        };
+
+        let mut bindings = Vec::new();
+        for ((name, value_type), value) in variables.into_iter().zip(values) {
+            let value = match value_type {
+                ValueType::Array => ExprKind::Array(runtime.read_array(value)?.into_iter()
+                    .map(|element| Expr::new(ExprKind::Constant(Atom::Num(element)), REPLAYED))
+                    .collect()),
+                ValueType::Bool => ExprKind::Constant(Atom::Boolean(value != 0)),
+                _ => ExprKind::Constant(Atom::Num(value)),
+            };
+            bindings.push((name, Expr::new(value, REPLAYED)));
+        }
+        self.bindings = bindings;
+        Ok(output)
     }
+}
 
-    // Get the bytecode
-    let ast = output.map_err(|err| eyre::eyre!("AST Error: {:#?}", err))?;
-    let bytecode = mir::compile(&ast)?;
-    if silent == false {
-        println!("===== Bytecode:\n{:#?}", bytecode);
-        println!();
+/// Locates an error of a session line in its source, one in the nodes added around it is reported without a position.
+fn locate_line(err: eyre::Report, input: &str) -> eyre::Report {
+    match err.downcast_ref::<diagnostic::SpanError>() {
+        Some(error) if error.span == REPLAYED => eyre::eyre!("{} in the bindings of the previous lines", error.message),
+        _ => diagnostic::locate(err, input),
     }
+}
 
-    // Compile to Wasm
-    let wasm = mir::to_wasm_module(&bytecode).wrap_err_with(|| "Failed to compile to Wasm")?;
-
-    // Run the Wasm
-    let config = Config::new();
-    let engine = Engine::new(&config).map_err(|err| eyre::eyre!("Failed to create engine: {:#?}", err))?;
-    let module = wasmtime::Module::new(&engine, wasm).map_err(|err| eyre::eyre!("Failed to create module: {:#?}", err))?;
-    let linker = wasmtime::Linker::new(&engine);
-    let mut store = wasmtime::Store::new(&engine, ());
-    let instance = linker.instantiate(&mut store, &module).map_err(|err| eyre::eyre!("Failed to instantiate module: {:#?}", err))?;
-
-    let func = instance.get_typed_func::<(), i64>(&mut store, "main").map_err(|err| eyre::eyre!("Failed to get function: {:#?}", err))?;
-    let result = func.call(&mut store, ()).map_err(|err| eyre::eyre!("Failed to call function: {:#?}", err))?;
-    Ok(format!("{:?}", result))
+fn identifier(name: &str, span: Span) -> Expr {
+    Expr::new(ExprKind::Constant(Atom::Identifier(name.to_string())), span)
+}
+
+fn any_node(expr: &Expr, f: &impl Fn(&Expr) -> bool) -> bool {
+    f(expr) || children(expr).into_iter().any(|child| any_node(child, f))
+}
+
+fn children(expr: &Expr) -> Vec<&Expr> {
+    match &expr.kind {
+        ExprKind::Let(_, inner) | ExprKind::Loop(inner) | ExprKind::Lambda(_, inner) | ExprKind::Member(inner, _) | ExprKind::UnaryOp(_, inner) => vec![inner],
+        ExprKind::IfElse(cond, then_expr, else_expr) => [Some(cond), Some(then_expr), else_expr.as_ref()].into_iter().flatten().map(|expr| expr.as_ref()).collect(),
+        ExprKind::While(lhs, rhs) | ExprKind::Index(lhs, rhs) | ExprKind::BinaryOp(lhs, _, rhs) | ExprKind::CompoundAssign(lhs, _, rhs) => vec![lhs, rhs],
+        ExprKind::Break(value) | ExprKind::Return(value) => value.as_deref().into_iter().collect(),
+        ExprKind::Call(callee, arg) => [Some(callee), arg.as_ref()].into_iter().flatten().map(|expr| expr.as_ref()).collect(),
+        ExprKind::Slice(array, start, end) => vec![array, start, end],
+        ExprKind::Block(exprs) | ExprKind::Array(exprs) => exprs.iter().collect(),
+        ExprKind::Object(fields) => fields.iter().map(|(_, expr)| expr).collect(),
+        ExprKind::Continue | ExprKind::Constant(_) | ExprKind::Import(_) => vec![],
+    }
 }
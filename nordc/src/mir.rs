@@ -1,11 +1,15 @@
-use std::collections::{HashMap};
-use crate::ast::{Atom, Expr, Opcode};
+use std::str::FromStr;
+
+use crate::ast::{Atom, Expr, ExprKind, Opcode, Span};
 use eyre::{ContextCompat, Result};
-use walrus::{ValType};
-use crate::mir_context::{MirContext, MirSharedContext};
+use walrus::{InstrSeqBuilder, LocalId, ValType};
+use walrus::ir::{LoadKind, MemArg, StoreKind};
+use crate::mir_context::MirContext;
+use crate::mir_var_context::MirVarContext;
+use crate::diagnostic::SpanError;
 
 /// Opcodes for the Nord's stack based virtual machine.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Mir {
     ConstI64(i64),
     AddI64,
@@ -13,219 +17,1499 @@ pub enum Mir {
     MulI64,
     DivI64,
     ModI64,
+    AndI64,
+    OrI64,
+    XorI64,
+    /// Shifts left by the amount on top of the stack, modulo 64.
+    ShlI64,
+    /// Shifts right by the amount on top of the stack modulo 64, filling in the sign bit.
+    ShrI64,
     GreaterThanI64,
     LessThanI64,
     EqualI64,
     NotEqualI64,
     GreaterThanOrEqualI64,
     LessThanOrEqualI64,
+    /// Absolute value, `i64::MIN` stays as it is like Wasm's wrapping arithmetic.
+    AbsI64,
+    MinI64,
+    MaxI64,
+    /// Raises the number below to the power on top of the stack, failing on overflow or a negative exponent.
+    PowI64,
     LocalGet(u32),
     LocalSet(u32),
     LocalTee(u32),
     Block(Vec<Mir>),
     Loop(Vec<Mir>),
+    /// Leaves the innermost loop with the value on top of the stack.
+    Break,
+    /// Jumps back to the start of the innermost loop.
+    Continue,
+    /// Returns from `main` with the value on top of the stack.
+    Return,
+    /// Runs the first arm when the value on top of the stack is not 0 and the second otherwise, both leaving an i64.
+    ///
+    /// Without a second arm there is no value when the condition is 0, so the first must leave nothing either.
     IfElse(Vec<Mir>, Option<Vec<Mir>>),
     Drop,
+    /// Prints the value on top of the stack through the host, leaving it in place.
+    PrintI64,
+    /// Allocates an array of the given length, leaving its address.
+    ///
+    /// Arrays live in linear memory as their length followed by the elements, all i64.
+    AllocArray(u32),
+    /// Reads the element at the index on top of the stack from the array below it, trapping when out of bounds.
+    ArrayGet,
+    /// Stores the value on top of the stack at the index and array below it, trapping when out of bounds.
+    ArraySet,
+    /// Compares the two arrays on top of the stack, equal when they have the same length and elements.
+    ArrayEqual,
+    /// Copies the elements from the start below to the end on top of the stack out of the array below them into a new
+    /// array, leaving its address.
+    ///
+    /// Negative indices count from the end of the array and both are clamped to it, a start after the end fails.
+    ArraySlice,
+    /// Does nothing, left by passes blanking out instructions in place and stripped by [`remove_nops`] before lowering.
+    Nop,
+}
+
+/// Options controlling how MIR is lowered to Wasm.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WasmOptions {
+    /// Trap when a local is read before it was ever assigned, instead of reading Wasm's default 0.
+    pub strict_locals: bool,
+    /// Number of i64 parameters `main` takes, bound to the first locals, see [`main_signature`].
+    pub params: u32,
+    /// Aborts with a step limit error once loops ran this many iterations in total.
+    pub max_steps: Option<u64>,
+    /// What addition, subtraction and multiplication do when the result does not fit in an i64.
+    pub overflow: Overflow,
+}
+
+/// Behavior of `+`, `-` and `*` on results out of the i64 range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overflow {
+    /// Wraps around in two's complement, like the plain Wasm instructions.
+    #[default]
+    Wrap,
+    /// Aborts with an integer overflow error.
+    Checked,
+    /// Clamps to `i64::MIN` or `i64::MAX`.
+    Saturate,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "wrap" => Ok(Overflow::Wrap),
+            "checked" => Ok(Overflow::Checked),
+            "saturate" => Ok(Overflow::Saturate),
+            _ => Err(format!("unknown overflow mode `{}`, expected wrap, checked or saturate", mode)),
+        }
+    }
+}
+
+impl WasmOptions {
+    /// Default options with `main` taking the parameters of the program.
+    pub fn for_program(ast: &Expr) -> Self {
+        Self::default().with_params_of(ast)
+    }
+    /// Sets the parameters of `main` to those of the program.
+    pub fn with_params_of(self, ast: &Expr) -> Self {
+        Self {
+            params: main_signature(ast).0.len() as u32,
+            ..self
+        }
+    }
+}
+
+/// Static type of a value, which the Wasm backend always carries as an i64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Unit,
+    Int,
+    /// Comparisons and boolean literals, carried as 0 or 1.
+    Bool,
+    /// Address of an array in linear memory, which the host reads with `Runtime::read_array`.
+    Array,
+}
+
+impl ValueType {
+    /// Render an i64 carrying a value of this type the way the user wrote it.
+    pub fn format(self, value: i64) -> String {
+        match self {
+            ValueType::Unit => "()".to_string(),
+            ValueType::Int => value.to_string(),
+            ValueType::Bool => (value != 0).to_string(),
+            // The elements are only known to the runtime holding the memory
+            ValueType::Array => format!("<array at {}>", value),
+        }
+    }
 }
 
 /// Convert an AST node to a sequence of bytecode instructions.
 pub fn compile(ast: &Expr) -> Result<Vec<Mir>> {
+    compile_typed(ast).map(|(bytecode, _)| bytecode)
+}
+/// Convert an AST node to a sequence of bytecode instructions, along with the type of the value `main` returns.
+pub fn compile_typed(ast: &Expr) -> Result<(Vec<Mir>, ValueType)> {
     let mut bytecode = Vec::new();
-    let mut locals = HashMap::new();
-    compile_expr(ast, &mut bytecode, &mut locals)?;
-    Ok(bytecode)
-}
-/// Compile an AST expression to bytecode.
-fn compile_expr(ast: &Expr, bytecode: &mut Vec<Mir>, locals: &mut HashMap<String, u32>) -> Result<()> {
-    match ast {
-        Expr::Constant(atom) => match atom {
-            Atom::Num(num) => bytecode.push(Mir::ConstI64(*num)),
-            Atom::Boolean(b) => bytecode.push(Mir::ConstI64(if *b { 1 } else { 0 })),
+    let mut vars = MirVarContext::new();
+    let (params, body) = main_signature(ast);
+    for param in params {
+        vars.declare(&param, ValueType::Int);
+    }
+    let mut value_type = compile_expr(body, &mut bytecode, &mut vars)?;
+    if !produces_value(body) {
+        // `main` always returns an i64, a unit program returns a placeholder
+        bytecode.push(Mir::ConstI64(0));
+        value_type = ValueType::Unit;
+    }
+    for &(return_type, span) in vars.returns() {
+        if (return_type == ValueType::Unit) != (value_type == ValueType::Unit) {
+            return Err(SpanError::new(span, format!(
+                "`return` of a {:?} value in a program whose result is {:?}, both must be unit or both a value",
+                return_type, value_type,
+            )).into());
+        }
+        // Values of different types, like a number and a boolean, are only known to be an i64
+        if return_type != value_type {
+            value_type = ValueType::Int;
+        }
+    }
+    fold_constants(&mut bytecode);
+    remove_nops(&mut bytecode);
+    Ok((bytecode, value_type))
+}
+/// Replace operations on constant operands by their result, including in nested sequences.
+///
+/// Operations that would trap, like a division by zero, are kept so they still trap at runtime.
+pub fn fold_constants(bytecode: &mut Vec<Mir>) {
+    let mut folded: Vec<Mir> = Vec::with_capacity(bytecode.len());
+    for mut op in bytecode.drain(..) {
+        match &mut op {
+            Mir::Block(ops) | Mir::Loop(ops) => fold_constants(ops),
+            Mir::IfElse(then_ops, else_ops) => {
+                fold_constants(then_ops);
+                if let Some(else_ops) = else_ops {
+                    fold_constants(else_ops);
+                }
+            }
+            _ => {}
+        }
+        // A block of a single constant is just that constant, nothing can branch to it
+        if let Mir::Block(ops) = &op {
+            if let [Mir::ConstI64(value)] = ops[..] {
+                op = Mir::ConstI64(value);
+            }
+        }
+        if let [.., Mir::ConstI64(lhs), Mir::ConstI64(rhs)] = folded[..] {
+            if let Some(value) = fold_binary(&op, lhs, rhs) {
+                folded.truncate(folded.len() - 2);
+                folded.push(Mir::ConstI64(value));
+                continue;
+            }
+        }
+        folded.push(op);
+    }
+    *bytecode = folded;
+}
+/// Evaluate a binary operation like the lowered Wasm does, `None` when it is not one or would trap.
+fn fold_binary(op: &Mir, lhs: i64, rhs: i64) -> Option<i64> {
+    match op {
+        // What an overflow gives depends on the mode it is lowered in, so those are left to the runtime
+        Mir::AddI64 => lhs.checked_add(rhs),
+        Mir::SubI64 => lhs.checked_sub(rhs),
+        Mir::MulI64 => lhs.checked_mul(rhs),
+        Mir::DivI64 => lhs.checked_div(rhs),
+        // Wasm's i64.rem_s of i64::MIN by -1 is 0 rather than a trap
+        Mir::ModI64 => if rhs == 0 { None } else { Some(lhs.wrapping_rem(rhs)) },
+        Mir::AndI64 => Some(lhs & rhs),
+        Mir::OrI64 => Some(lhs | rhs),
+        Mir::XorI64 => Some(lhs ^ rhs),
+        // Like Wasm, the shift amount is taken modulo 64
+        Mir::ShlI64 => Some(lhs.wrapping_shl(rhs as u32)),
+        Mir::ShrI64 => Some(lhs.wrapping_shr(rhs as u32)),
+        Mir::GreaterThanI64 => Some((lhs > rhs) as i64),
+        Mir::LessThanI64 => Some((lhs < rhs) as i64),
+        Mir::EqualI64 => Some((lhs == rhs) as i64),
+        Mir::NotEqualI64 => Some((lhs != rhs) as i64),
+        Mir::GreaterThanOrEqualI64 => Some((lhs >= rhs) as i64),
+        Mir::LessThanOrEqualI64 => Some((lhs <= rhs) as i64),
+        Mir::MinI64 => Some(lhs.min(rhs)),
+        Mir::MaxI64 => Some(lhs.max(rhs)),
+        // Overflows and negative exponents are left to fail at runtime
+        Mir::PowI64 => u32::try_from(rhs).ok().and_then(|exp| lhs.checked_pow(exp)),
+        _ => None,
+    }
+}
+/// Evaluate a program made of a single integer expression of number literals, `None` when it has to be compiled.
+///
+/// Operations that would trap or overflow are left to the compiled program, so the value always matches its result.
+pub fn const_eval(ast: &Expr) -> Option<i64> {
+    match &ast.kind {
+        ExprKind::Constant(Atom::Num(value)) => Some(*value),
+        ExprKind::Block(exprs) if exprs.len() == 1 => const_eval(&exprs[0]),
+        ExprKind::UnaryOp(Opcode::Neg, expr) => const_eval(expr)?.checked_neg(),
+        // Comparisons result in booleans, which are printed differently
+        ExprKind::BinaryOp(lhs, opcode, rhs) => match opcode {
+            Opcode::Add | Opcode::Mul | Opcode::Sub | Opcode::Div | Opcode::Mod
+            | Opcode::BitAnd | Opcode::BitOr | Opcode::BitXor | Opcode::Shl | Opcode::Shr => {
+                fold_binary(&binary_instruction(opcode)?, const_eval(lhs)?, const_eval(rhs)?)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+/// Remove every `Nop`, including those in nested sequences.
+pub fn remove_nops(bytecode: &mut Vec<Mir>) {
+    bytecode.retain(|op| !matches!(op, Mir::Nop));
+    for op in bytecode {
+        match op {
+            Mir::Block(ops) | Mir::Loop(ops) => remove_nops(ops),
+            Mir::IfElse(then_ops, else_ops) => {
+                remove_nops(then_ops);
+                if let Some(else_ops) = else_ops {
+                    remove_nops(else_ops);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+/// Split a program consisting of a single, possibly curried, lambda into the parameters of `main` and its body.
+pub fn main_signature(ast: &Expr) -> (Vec<String>, &Expr) {
+    let mut body = match &ast.kind {
+        ExprKind::Block(exprs) if exprs.len() == 1 => &exprs[0],
+        _ => ast,
+    };
+    if !matches!(body.kind, ExprKind::Lambda(..)) {
+        return (vec![], ast);
+    }
+    let mut params = Vec::new();
+    while let ExprKind::Lambda(param, inner) = &body.kind {
+        params.extend(param.iter().cloned());
+        body = inner;
+    }
+    (params, body)
+}
+/// Whether evaluating the expression leaves a value, bindings, assignments and empty blocks are unit.
+pub fn produces_value(ast: &Expr) -> bool {
+    match &ast.kind {
+        ExprKind::Let(..) | ExprKind::BinaryOp(_, Opcode::Assign, _) | ExprKind::CompoundAssign(..) | ExprKind::Return(None) | ExprKind::Import(_) => false,
+        ExprKind::Block(exprs) => exprs.last().is_some_and(produces_value),
+        ExprKind::IfElse(_, then_expr, else_expr) => if_produces_value(then_expr, else_expr.as_deref()),
+        ExprKind::Loop(body) => loop_breaks(body).contains(&true),
+        ExprKind::While(_, body) => produces_value(body),
+        _ => true,
+    }
+}
+/// Whether an `if` with these arms leaves a value, an arm that never falls through takes the other's shape.
+fn if_produces_value(then_expr: &Expr, else_expr: Option<&Expr>) -> bool {
+    let then_value = produces_value(then_expr);
+    let else_value = else_expr.is_some_and(produces_value);
+    if diverges(then_expr) {
+        else_value
+    } else if else_expr.is_some_and(diverges) {
+        then_value
+    } else {
+        then_value && else_value
+    }
+}
+/// Whether control never falls through the expression, because it always returns, breaks or continues.
+fn diverges(ast: &Expr) -> bool {
+    match &ast.kind {
+        ExprKind::Return(_) | ExprKind::Break(_) | ExprKind::Continue => true,
+        ExprKind::Block(exprs) => exprs.iter().any(diverges),
+        ExprKind::IfElse(cond, then_expr, else_expr) => diverges(cond) || (diverges(then_expr) && else_expr.as_deref().is_some_and(diverges)),
+        ExprKind::Let(_, expr) => diverges(expr),
+        _ => false,
+    }
+}
+/// Whether each `break` leaving the loop with this body carries a value, breaks of nested loops excluded.
+fn loop_breaks(body: &Expr) -> Vec<bool> {
+    fn collect(ast: &Expr, breaks: &mut Vec<bool>) {
+        match &ast.kind {
+            ExprKind::Break(value) => {
+                breaks.push(value.is_some());
+                if let Some(value) = value {
+                    collect(value, breaks);
+                }
+            }
+            // Breaks in there leave the nested loop or function instead
+            ExprKind::Loop(_) | ExprKind::While(..) | ExprKind::Lambda(..) => {}
+            ExprKind::Let(_, expr) | ExprKind::UnaryOp(_, expr) | ExprKind::Member(expr, _) | ExprKind::Return(Some(expr)) => collect(expr, breaks),
+            ExprKind::IfElse(cond, then_expr, else_expr) => {
+                collect(cond, breaks);
+                collect(then_expr, breaks);
+                if let Some(else_expr) = else_expr {
+                    collect(else_expr, breaks);
+                }
+            }
+            ExprKind::Block(exprs) | ExprKind::Array(exprs) => exprs.iter().for_each(|expr| collect(expr, breaks)),
+            ExprKind::Object(members) => members.iter().for_each(|(_, expr)| collect(expr, breaks)),
+            ExprKind::Call(callee, arg) => {
+                collect(callee, breaks);
+                if let Some(arg) = arg {
+                    collect(arg, breaks);
+                }
+            }
+            ExprKind::Index(lhs, rhs) | ExprKind::BinaryOp(lhs, _, rhs) | ExprKind::CompoundAssign(lhs, _, rhs) => {
+                collect(lhs, breaks);
+                collect(rhs, breaks);
+            }
+            ExprKind::Slice(array, start, end) => {
+                collect(array, breaks);
+                collect(start, breaks);
+                collect(end, breaks);
+            }
+            ExprKind::Continue | ExprKind::Constant(_) | ExprKind::Return(None) | ExprKind::Import(_) => {}
+        }
+    }
+    let mut breaks = Vec::new();
+    collect(body, &mut breaks);
+    breaks
+}
+/// Compile an AST expression to bytecode, returning the type of the value it leaves.
+fn compile_expr(ast: &Expr, bytecode: &mut Vec<Mir>, vars: &mut MirVarContext) -> Result<ValueType> {
+    let value_type = match &ast.kind {
+        ExprKind::Constant(atom) => match atom {
+            Atom::Num(num) => {
+                bytecode.push(Mir::ConstI64(*num));
+                ValueType::Int
+            }
+            Atom::Boolean(b) => {
+                bytecode.push(Mir::ConstI64(if *b { 1 } else { 0 }));
+                ValueType::Bool
+            }
             Atom::Identifier(ident) => {
-                let index = locals.len() as u32;
-                let index = locals.entry(ident.clone()).or_insert(index);
-                bytecode.push(Mir::LocalGet(*index));
+                let index = vars.get(ident).ok_or_else(|| SpanError::new(ast.span, format!("Unknown variable: {}", ident)))?;
+                bytecode.push(Mir::LocalGet(index));
+                vars.type_of(index)
             }
-            _ => return Err(eyre::eyre!("Unsupported atom: {:?}", atom)),
+            _ => return Err(SpanError::new(ast.span, format!("Unsupported atom: {:?}", atom)).into()),
         },
-        Expr::BinaryOp(lhs, opcode, rhs) => {
-            compile_expr(lhs, bytecode, locals)?;
-            compile_expr(rhs, bytecode, locals)?;
-            match opcode {
-                Opcode::Add => bytecode.push(Mir::AddI64),
-                Opcode::Mul => bytecode.push(Mir::MulI64),
-                Opcode::Sub => bytecode.push(Mir::SubI64),
-                Opcode::Div => bytecode.push(Mir::DivI64),
-                Opcode::Mod => bytecode.push(Mir::ModI64),
-                Opcode::Greater => bytecode.push(Mir::GreaterThanI64),
-                Opcode::Less => bytecode.push(Mir::LessThanI64),
-                Opcode::Equal => bytecode.push(Mir::EqualI64),
-                Opcode::NotEqual => bytecode.push(Mir::NotEqualI64),
-                Opcode::GreaterEqual => bytecode.push(Mir::GreaterThanOrEqualI64),
-                Opcode::LessEqual => bytecode.push(Mir::LessThanOrEqualI64),
-                Opcode::Assign => {
-                    if let Expr::Constant(Atom::Identifier(ident)) = &**lhs {
-                        let index = locals.get(ident).copied().wrap_err_with(|| format!("Unknown variable: {}", ident))?;
-                        bytecode.push(Mir::LocalSet(index));
-                    } else {
-                        return Err(eyre::eyre!("Invalid assignment target: {:?}", lhs));
-                    }
+        ExprKind::BinaryOp(lhs, Opcode::Assign, rhs) => {
+            if let ExprKind::Constant(Atom::Identifier(ident)) = &lhs.kind {
+                let index = vars.get(ident).ok_or_else(|| SpanError::new(lhs.span, format!("Unknown variable: {}", ident)))?;
+                compile_expr(rhs, bytecode, vars)?;
+                bytecode.push(Mir::LocalSet(index));
+            } else if let ExprKind::Index(array, index) = &lhs.kind {
+                compile_expr(array, bytecode, vars)?;
+                compile_expr(index, bytecode, vars)?;
+                compile_expr(rhs, bytecode, vars)?;
+                bytecode.push(Mir::ArraySet);
+            } else {
+                return Err(SpanError::new(lhs.span, "Invalid assignment target").into());
+            }
+            ValueType::Unit
+        }
+        ExprKind::CompoundAssign(target, opcode, value) => {
+            let op = binary_instruction(opcode).ok_or_else(|| SpanError::new(ast.span, format!("Unsupported operator `{}=`", opcode.symbol())))?;
+            match &target.kind {
+                ExprKind::Constant(Atom::Identifier(ident)) => {
+                    let index = vars.get(ident).ok_or_else(|| SpanError::new(target.span, format!("Unknown variable: {}", ident)))?;
+                    bytecode.push(Mir::LocalGet(index));
+                    compile_expr(value, bytecode, vars)?;
+                    bytecode.push(op);
+                    bytecode.push(Mir::LocalSet(index));
                 }
-                _ => return Err(eyre::eyre!("Unsupported opcode: {:?}", opcode)),
+                ExprKind::Index(array, index) => {
+                    // The array and index are evaluated once, for both the read and the write
+                    let array_local = vars.add_hidden();
+                    let index_local = vars.add_hidden();
+                    compile_expr(array, bytecode, vars)?;
+                    bytecode.push(Mir::LocalSet(array_local));
+                    compile_expr(index, bytecode, vars)?;
+                    bytecode.push(Mir::LocalSet(index_local));
+                    bytecode.extend([Mir::LocalGet(array_local), Mir::LocalGet(index_local)]);
+                    bytecode.extend([Mir::LocalGet(array_local), Mir::LocalGet(index_local), Mir::ArrayGet]);
+                    compile_expr(value, bytecode, vars)?;
+                    bytecode.push(op);
+                    bytecode.push(Mir::ArraySet);
+                }
+                _ => return Err(SpanError::new(target.span, "Invalid assignment target").into()),
+            }
+            ValueType::Unit
+        }
+        ExprKind::BinaryOp(lhs, opcode, rhs) => {
+            let lhs_type = compile_expr(lhs, bytecode, vars)?;
+            let rhs_type = compile_expr(rhs, bytecode, vars)?;
+            if (lhs_type, rhs_type) == (ValueType::Array, ValueType::Array) && matches!(opcode, Opcode::Equal | Opcode::NotEqual) {
+                // Arrays compare by their contents rather than their addresses
+                bytecode.push(Mir::ArrayEqual);
+                if let Opcode::NotEqual = opcode {
+                    bytecode.push(Mir::ConstI64(0));
+                    bytecode.push(Mir::EqualI64);
+                }
+                return Ok(ValueType::Bool);
+            }
+            let op = binary_instruction(opcode).ok_or_else(|| SpanError::new(ast.span, format!("Unsupported operator `{}`", opcode.symbol())))?;
+            bytecode.push(op);
+            match opcode {
+                Opcode::Add | Opcode::Mul | Opcode::Sub | Opcode::Div | Opcode::Mod => ValueType::Int,
+                Opcode::BitAnd | Opcode::BitOr | Opcode::BitXor | Opcode::Shl | Opcode::Shr => ValueType::Int,
+                _ => ValueType::Bool,
             }
         }
-        Expr::UnaryOp(opcode, expr) => {
+        ExprKind::UnaryOp(opcode, expr) => {
             match opcode {
                 Opcode::Neg => {
                     bytecode.push(Mir::ConstI64(0));
-                    compile_expr(expr, bytecode, locals)?;
+                    compile_expr(expr, bytecode, vars)?;
                     bytecode.push(Mir::SubI64);
                 }
-                _ => return Err(eyre::eyre!("Unsupported opcode: {:?}", opcode)),
+                Opcode::Increment | Opcode::Decrement => {
+                    let ExprKind::Constant(Atom::Identifier(ident)) = &expr.kind else {
+                        return Err(SpanError::new(expr.span, format!("`{}` can only be applied to a variable", opcode.symbol())).into());
+                    };
+                    let index = vars.get(ident).ok_or_else(|| SpanError::new(expr.span, format!("Unknown variable: {}", ident)))?;
+                    bytecode.push(Mir::LocalGet(index));
+                    bytecode.push(Mir::ConstI64(1));
+                    bytecode.push(if let Opcode::Increment = opcode { Mir::AddI64 } else { Mir::SubI64 });
+                    bytecode.push(Mir::LocalTee(index));
+                }
+                _ => return Err(SpanError::new(ast.span, format!("Unsupported operator `{}`", opcode.symbol())).into()),
             }
+            ValueType::Int
         }
-        Expr::Let(ident, expr) => {
-            compile_expr(expr, bytecode, locals)?;
-            let index = locals.len() as u32;
-            locals.insert(ident.clone(), index);
+        ExprKind::Let(ident, expr) => {
+            let value_type = compile_expr(expr, bytecode, vars)?;
+            let index = vars.declare(ident, value_type);
             bytecode.push(Mir::LocalSet(index));
+            ValueType::Unit
         }
-        Expr::Block(exprs) => {
+        ExprKind::Block(exprs) => {
             let mut block_vec = Vec::new();
-            for expr in exprs {
-                compile_expr(expr, &mut block_vec, locals)?;
+            let mut value_type = ValueType::Unit;
+            vars.push_scope();
+            for (i, expr) in exprs.iter().enumerate() {
+                // Only the last expression is the block's value
+                if i + 1 < exprs.len() {
+                    compile_statement(expr, &mut block_vec, vars)?;
+                } else {
+                    value_type = compile_expr(expr, &mut block_vec, vars)?;
+                }
             }
-            bytecode.push(Mir::Block(block_vec));
-        }
-        Expr::IfElse(cond, then_expr, else_expr) => {
-            compile_expr(cond, bytecode, locals)?;
-            let mut then_vec = Vec::new();
-            compile_expr(then_expr, &mut then_vec, locals)?;
-            let mut else_vec = None;
-            if let Some(else_expr) = else_expr {
-                let mut else_vec_some = Vec::new();
-                compile_expr(else_expr, &mut else_vec_some, locals)?;
-                else_vec = Some(else_vec_some);
+            vars.pop_scope();
+            if produces_value(ast) {
+                bytecode.push(Mir::Block(block_vec));
+            } else {
+                // Nothing can branch to a block, so a unit block is just its statements
+                bytecode.extend(block_vec);
             }
-            bytecode.push(Mir::IfElse(then_vec, else_vec));
+            value_type
         }
-        _ => return Err(eyre::eyre!("Unsupported expression: {:?}", ast)),
-    }
-
-    Ok(())
-}
-
-pub fn mir_to_wasm(op: &Mir, context: MirSharedContext) -> Result<()> {
-    context.borrow_mut().function_body(|mut builder| {
-        match op {
-            Mir::ConstI64(num) => {
-                builder.i64_const(*num);
+        ExprKind::IfElse(cond, then_expr, else_expr) => compile_if(ast.span, cond, then_expr, else_expr.as_deref(), false, bytecode, vars)?,
+        ExprKind::Loop(body) => compile_loop(body, false, bytecode, vars)?,
+        ExprKind::While(cond, body) => {
+            // The loop's value is the last value of its body, kept in a local no identifier can name
+            let result = vars.add_hidden();
+            let mut loop_vec = Vec::new();
+            vars.enter_loop();
+            compile_expr(cond, &mut loop_vec, vars)?;
+            // The exit is the else arm, so a comparison as the condition branches as the i32 it leaves
+            loop_vec.push(Mir::IfElse(vec![Mir::ConstI64(0)], Some(vec![Mir::LocalGet(result), Mir::Break])));
+            loop_vec.push(Mir::Drop);
+            let body_type = compile_expr(body, &mut loop_vec, vars)?;
+            vars.exit_loop();
+            if body_type == ValueType::Unit {
+                loop_vec.push(Mir::ConstI64(0));
             }
-            Mir::AddI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64Add);
+            loop_vec.push(Mir::LocalSet(result));
+
+            // A body that never runs leaves the zero of its type, an empty array for an array
+            bytecode.push(match body_type {
+                ValueType::Array => Mir::AllocArray(0),
+                _ => Mir::ConstI64(0),
+            });
+            bytecode.push(Mir::LocalSet(result));
+            bytecode.push(Mir::Loop(loop_vec));
+            if body_type == ValueType::Unit {
+                bytecode.push(Mir::Drop);
             }
-            Mir::SubI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64Sub);
+            body_type
+        }
+        ExprKind::Break(value) => {
+            if !vars.in_loop() {
+                return Err(SpanError::new(ast.span, "`break` outside of a loop").into());
             }
-            Mir::MulI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64Mul);
+            match value {
+                Some(value) => {
+                    compile_expr(value, bytecode, vars)?;
+                }
+                None => bytecode.push(Mir::ConstI64(0)),
             }
-            Mir::DivI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64DivS);
+            bytecode.push(Mir::Break);
+            // Never falls through, the value only satisfies the enclosing sequence
+            ValueType::Int
+        }
+        ExprKind::Continue => {
+            if !vars.in_loop() {
+                return Err(SpanError::new(ast.span, "`continue` outside of a loop").into());
             }
-            Mir::ModI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64RemS);
+            bytecode.push(Mir::Continue);
+            ValueType::Int
+        }
+        ExprKind::Return(value) => {
+            // A bare return still hands `main` its placeholder, but leaves nothing for the enclosing sequence
+            let value_type = match value {
+                Some(value) => compile_expr(value, bytecode, vars)?,
+                None => {
+                    bytecode.push(Mir::ConstI64(0));
+                    ValueType::Unit
+                }
+            };
+            bytecode.push(Mir::Return);
+            vars.add_return(value_type, ast.span);
+            value_type
+        }
+        ExprKind::Array(elems) => {
+            // The address is kept in a local no identifier can name while the elements are stored
+            let array = vars.add_hidden();
+            bytecode.push(Mir::AllocArray(elems.len() as u32));
+            bytecode.push(Mir::LocalSet(array));
+            for (i, elem) in elems.iter().enumerate() {
+                bytecode.push(Mir::LocalGet(array));
+                bytecode.push(Mir::ConstI64(i as i64));
+                compile_expr(elem, bytecode, vars)?;
+                bytecode.push(Mir::ArraySet);
             }
-            Mir::GreaterThanI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64GtS);
-                builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            bytecode.push(Mir::LocalGet(array));
+            ValueType::Array
+        }
+        ExprKind::Index(array, index) => {
+            compile_expr(array, bytecode, vars)?;
+            compile_expr(index, bytecode, vars)?;
+            bytecode.push(Mir::ArrayGet);
+            ValueType::Int
+        }
+        ExprKind::Slice(array, start, end) => compile_slice(array, start, end, bytecode, vars)?,
+        ExprKind::Call(callee, arg) => match (&callee.kind, arg) {
+            (ExprKind::Constant(Atom::Identifier(name)), Some(arg)) if name == "print" => {
+                let value_type = compile_expr(arg, bytecode, vars)?;
+                bytecode.push(Mir::PrintI64);
+                value_type
             }
-            Mir::LessThanI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64LtS);
-                builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            (ExprKind::Constant(Atom::Identifier(name)), Some(arg)) if name == "abs" => {
+                compile_number_arg(name, arg, bytecode, vars)?;
+                bytecode.push(Mir::AbsI64);
+                ValueType::Int
             }
-            Mir::EqualI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64Eq);
-                builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            // Like every call of several arguments, the binary builtins are curried: `min(a)(b)`
+            (ExprKind::Call(inner, Some(lhs)), Some(rhs)) if binary_builtin(&inner.kind).is_some() => {
+                let (name, op) = binary_builtin(&inner.kind).wrap_err("Not a builtin")?;
+                compile_number_arg(name, lhs, bytecode, vars)?;
+                compile_number_arg(name, rhs, bytecode, vars)?;
+                bytecode.push(op);
+                ValueType::Int
             }
-            Mir::NotEqualI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64Ne);
-                builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            (callee, _) if binary_builtin(callee).is_some() => {
+                let (name, _) = binary_builtin(callee).wrap_err("Not a builtin")?;
+                return Err(SpanError::new(ast.span, format!("`{}` takes two numbers, call it as {}(a)(b)", name, name)).into());
             }
-            Mir::GreaterThanOrEqualI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64GeS);
-                builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            (ExprKind::Call(inner, Some(start)), Some(end)) if slice_array(&inner.kind).is_some() => {
+                let array = slice_array(&inner.kind).wrap_err("Not a slice")?;
+                compile_slice(array, start, end, bytecode, vars)?
             }
-            Mir::LessThanOrEqualI64 => {
-                builder.binop(walrus::ir::BinaryOp::I64LeS);
-                builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            (callee, _) if is_identifier(callee, "slice") || slice_array(callee).is_some() => {
+                return Err(SpanError::new(ast.span, "`slice` takes an array and two indices, call it as slice(a)(start)(end)").into());
             }
-            Mir::LocalGet(index) => {
-                if let Some(local) = context.borrow_mut().get_local(*index) {
-                    builder.local_get(local);
-                } else {
-                    eyre::bail!("Local not found")
-                }
+            _ => return Err(SpanError::new(ast.span, "Unsupported call").into()),
+        },
+        ExprKind::Import(_) => {
+            return Err(SpanError::new(ast.span, "`import` is only allowed at the top level of a program").into());
+        }
+        _ => return Err(SpanError::new(ast.span, "Unsupported expression").into()),
+    };
+
+    Ok(value_type)
+}
+
+/// The instruction of a binary operator taking two numbers, `None` for the logical operators and assignment.
+fn binary_instruction(opcode: &Opcode) -> Option<Mir> {
+    match opcode {
+        Opcode::Add => Some(Mir::AddI64),
+        Opcode::Mul => Some(Mir::MulI64),
+        Opcode::Sub => Some(Mir::SubI64),
+        Opcode::Div => Some(Mir::DivI64),
+        Opcode::Mod => Some(Mir::ModI64),
+        Opcode::Greater => Some(Mir::GreaterThanI64),
+        Opcode::Less => Some(Mir::LessThanI64),
+        Opcode::Equal => Some(Mir::EqualI64),
+        Opcode::NotEqual => Some(Mir::NotEqualI64),
+        Opcode::GreaterEqual => Some(Mir::GreaterThanOrEqualI64),
+        Opcode::LessEqual => Some(Mir::LessThanOrEqualI64),
+        Opcode::BitAnd => Some(Mir::AndI64),
+        Opcode::BitOr => Some(Mir::OrI64),
+        Opcode::BitXor => Some(Mir::XorI64),
+        Opcode::Shl => Some(Mir::ShlI64),
+        Opcode::Shr => Some(Mir::ShrI64),
+        _ => None,
+    }
+}
+/// The name and instruction of a builtin taking two numbers, if the callee names one.
+fn binary_builtin(callee: &ExprKind) -> Option<(&'static str, Mir)> {
+    match callee {
+        ExprKind::Constant(Atom::Identifier(name)) => match name.as_str() {
+            "min" => Some(("min", Mir::MinI64)),
+            "max" => Some(("max", Mir::MaxI64)),
+            "pow" => Some(("pow", Mir::PowI64)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+/// The array `slice` is applied to, if the callee is `slice(array)`.
+fn slice_array(callee: &ExprKind) -> Option<&Expr> {
+    match callee {
+        ExprKind::Call(function, Some(array)) if is_identifier(&function.kind, "slice") => Some(array),
+        _ => None,
+    }
+}
+/// Compile a copy of the elements of an array from `start` up to `end`, of `slice(a)(start)(end)` or `a[start..end]`.
+fn compile_slice(array: &Expr, start: &Expr, end: &Expr, bytecode: &mut Vec<Mir>, vars: &mut MirVarContext) -> Result<ValueType> {
+    match compile_expr(array, bytecode, vars)? {
+        ValueType::Array => {}
+        value_type => return Err(SpanError::new(array.span, format!("`slice` expects an array, got {:?}", value_type)).into()),
+    }
+    compile_number_arg("slice", start, bytecode, vars)?;
+    compile_number_arg("slice", end, bytecode, vars)?;
+    bytecode.push(Mir::ArraySlice);
+    Ok(ValueType::Array)
+}
+fn is_identifier(expr: &ExprKind, name: &str) -> bool {
+    matches!(expr, ExprKind::Constant(Atom::Identifier(ident)) if ident == name)
+}
+/// Compile an argument of a numeric builtin, rejecting booleans and unit.
+fn compile_number_arg(builtin: &str, arg: &Expr, bytecode: &mut Vec<Mir>, vars: &mut MirVarContext) -> Result<()> {
+    match compile_expr(arg, bytecode, vars)? {
+        ValueType::Int => Ok(()),
+        value_type => Err(SpanError::new(arg.span, format!("`{}` expects a number, got {:?}", builtin, value_type)).into()),
+    }
+}
+/// Compile an expression whose value is discarded.
+fn compile_statement(ast: &Expr, bytecode: &mut Vec<Mir>, vars: &mut MirVarContext) -> Result<()> {
+    match &ast.kind {
+        ExprKind::IfElse(cond, then_expr, else_expr) => compile_if(ast.span, cond, then_expr, else_expr.as_deref(), true, bytecode, vars).map(drop),
+        ExprKind::Loop(body) => compile_loop(body, true, bytecode, vars).map(drop),
+        ExprKind::Block(exprs) => {
+            // Nothing can branch to a block, so a discarded block is just its statements
+            vars.push_scope();
+            for expr in exprs {
+                compile_statement(expr, bytecode, vars)?;
             }
-            Mir::LocalSet(index) => {
-                let index = context.borrow_mut().get_or_add_local(*index, walrus::ValType::I64);
-                builder.local_set(index);
+            vars.pop_scope();
+            Ok(())
+        }
+        _ => {
+            compile_expr(ast, bytecode, vars)?;
+            if produces_value(ast) {
+                bytecode.push(Mir::Drop);
             }
-            Mir::LocalTee(index) => {
-                let index = context.borrow_mut().get_or_add_local(*index, walrus::ValType::I64);
-                builder.local_tee(index);
+            Ok(())
+        }
+    }
+}
+/// Compile an `if`, whose arms must agree on producing a value unless the result is discarded.
+///
+/// An arm that never falls through, like one ending in a `return`, agrees with either.
+fn compile_if(span: Span, cond: &Expr, then_expr: &Expr, else_expr: Option<&Expr>, discard: bool, bytecode: &mut Vec<Mir>, vars: &mut MirVarContext) -> Result<ValueType> {
+    let then_value = produces_value(then_expr);
+    let else_value = else_expr.is_some_and(produces_value);
+    let then_diverges = diverges(then_expr);
+    let else_diverges = else_expr.is_some_and(diverges);
+    if !discard && then_value != else_value && !then_diverges && !else_diverges {
+        // A missing else has no value to fall back on, it is an error rather than an implicit 0
+        if else_expr.is_none() {
+            return Err(SpanError::new(span, "`if` without `else` cannot be used as a value, add an else branch").into());
+        }
+        let describe = |value| if value { "i64" } else { "unit" };
+        return Err(SpanError::new(span, format!(
+            "Branches of `if` produce different types: then branch is {}, else branch is {}",
+            describe(then_value), describe(else_value),
+        )).into());
+    }
+
+    compile_expr(cond, bytecode, vars)?;
+    // The lowered `if` always yields an i64, unit arms push a placeholder that is dropped afterwards
+    let mut then_vec = Vec::new();
+    let then_type = compile_expr(then_expr, &mut then_vec, vars)?;
+    if !then_value {
+        then_vec.push(Mir::ConstI64(0));
+    }
+    let mut else_vec = Vec::new();
+    let mut else_type = ValueType::Unit;
+    if let Some(else_expr) = else_expr {
+        else_type = compile_expr(else_expr, &mut else_vec, vars)?;
+    }
+    if !else_value {
+        else_vec.push(Mir::ConstI64(0));
+    }
+    bytecode.push(Mir::IfElse(then_vec, Some(else_vec)));
+    if discard || !if_produces_value(then_expr, else_expr) {
+        bytecode.push(Mir::Drop);
+        return Ok(ValueType::Unit);
+    }
+    // Arms of different value types, like a number and a boolean, are only known to be an i64
+    Ok(match (then_diverges, else_diverges) {
+        (true, false) => else_type,
+        (false, true) => then_type,
+        _ if then_type == else_type => then_type,
+        _ => ValueType::Int,
+    })
+}
+/// Compile a `loop`, whose breaks must agree on carrying a value unless the result is discarded.
+fn compile_loop(body: &Expr, discard: bool, bytecode: &mut Vec<Mir>, vars: &mut MirVarContext) -> Result<ValueType> {
+    let breaks = loop_breaks(body);
+    let value = breaks.contains(&true);
+    if !discard && value && breaks.contains(&false) {
+        return Err(SpanError::new(body.span, "Loop produces i64 but has a `break` without a value").into());
+    }
+
+    // The lowered loop always yields an i64, a bare break leaves a placeholder that is dropped afterwards
+    let mut loop_vec = Vec::new();
+    vars.enter_loop();
+    compile_statement(body, &mut loop_vec, vars)?;
+    vars.exit_loop();
+    bytecode.push(Mir::Loop(loop_vec));
+    if discard || !value {
+        bytecode.push(Mir::Drop);
+        return Ok(ValueType::Unit);
+    }
+    Ok(ValueType::Int)
+}
+
+pub fn mir_to_wasm(op: &Mir, builder: &mut InstrSeqBuilder, context: &mut MirContext) -> Result<()> {
+    match op {
+        Mir::ConstI64(num) => {
+            builder.i64_const(*num);
+        }
+        Mir::AddI64 | Mir::SubI64 | Mir::MulI64 => arithmetic(op, builder, context),
+        Mir::DivI64 => {
+            builder.binop(walrus::ir::BinaryOp::I64DivS);
+        }
+        Mir::ModI64 => {
+            builder.binop(walrus::ir::BinaryOp::I64RemS);
+        }
+        Mir::AndI64 => {
+            builder.binop(walrus::ir::BinaryOp::I64And);
+        }
+        Mir::OrI64 => {
+            builder.binop(walrus::ir::BinaryOp::I64Or);
+        }
+        Mir::XorI64 => {
+            builder.binop(walrus::ir::BinaryOp::I64Xor);
+        }
+        Mir::ShlI64 => {
+            builder.binop(walrus::ir::BinaryOp::I64Shl);
+        }
+        Mir::ShrI64 => {
+            builder.binop(walrus::ir::BinaryOp::I64ShrS);
+        }
+        Mir::GreaterThanI64 | Mir::LessThanI64 | Mir::EqualI64 | Mir::NotEqualI64 | Mir::GreaterThanOrEqualI64 | Mir::LessThanOrEqualI64 => {
+            builder.binop(comparison_binop(op).wrap_err("Not a comparison")?);
+            builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+        }
+        Mir::AbsI64 => {
+            let value = context.add_scratch_local(ValType::I64);
+            builder.local_set(value);
+            builder.i64_const(0);
+            builder.local_get(value);
+            builder.binop(walrus::ir::BinaryOp::I64Sub);
+            builder.local_get(value);
+            builder.local_get(value);
+            builder.i64_const(0);
+            builder.binop(walrus::ir::BinaryOp::I64LtS);
+            // Keeps the negated value when the original is negative
+            builder.select(None);
+        }
+        Mir::MinI64 | Mir::MaxI64 => {
+            let rhs = context.add_scratch_local(ValType::I64);
+            let lhs = context.add_scratch_local(ValType::I64);
+            builder.local_set(rhs);
+            builder.local_tee(lhs);
+            builder.local_get(rhs);
+            builder.local_get(lhs);
+            builder.local_get(rhs);
+            builder.binop(if *op == Mir::MinI64 { walrus::ir::BinaryOp::I64LtS } else { walrus::ir::BinaryOp::I64GtS });
+            // Keeps the left-hand side when it wins the comparison
+            builder.select(None);
+        }
+        Mir::PowI64 => {
+            let exponent = context.add_scratch_local(ValType::I64);
+            let base = context.add_scratch_local(ValType::I64);
+            let result = context.add_scratch_local(ValType::I64);
+            let product = context.add_scratch_local(ValType::I64);
+            let negative = context.get_or_add_import("env", "negative_exponent", &[], &[]);
+            let overflow = context.get_or_add_import("env", "integer_overflow", &[], &[]);
+            builder.local_set(exponent);
+            builder.local_set(base);
+            builder.local_get(exponent);
+            builder.i64_const(0);
+            builder.binop(walrus::ir::BinaryOp::I64LtS);
+            builder.if_else(None, |then| {
+                then.call(negative);
+                then.unreachable();
+            }, |_| {});
+            builder.i64_const(1);
+            builder.local_set(result);
+            // Square and multiply, one iteration per bit of the exponent
+            builder.block(None, |done| {
+                let done_id = done.id();
+                done.loop_(None, |head| {
+                    let head_id = head.id();
+                    head.local_get(exponent);
+                    head.unop(walrus::ir::UnaryOp::I64Eqz);
+                    head.br_if(done_id);
+                    head.local_get(exponent);
+                    head.i64_const(1);
+                    head.binop(walrus::ir::BinaryOp::I64And);
+                    head.unop(walrus::ir::UnaryOp::I32WrapI64);
+                    head.if_else(None, |then| {
+                        checked_mul(result, base, result, product, overflow, then);
+                    }, |_| {});
+                    head.local_get(exponent);
+                    head.i64_const(1);
+                    head.binop(walrus::ir::BinaryOp::I64ShrS);
+                    head.local_tee(exponent);
+                    head.unop(walrus::ir::UnaryOp::I64Eqz);
+                    head.br_if(done_id);
+                    // The base is only squared while bits remain, so its overflow is the result's
+                    checked_mul(base, base, base, product, overflow, head);
+                    head.br(head_id);
+                });
+            });
+            builder.local_get(result);
+        }
+        Mir::LocalGet(index) => {
+            let local = context.get_local(*index).wrap_err("Local not found")?;
+            if context.options.strict_locals {
+                let flag = context.get_or_add_init_flag(*index);
+                builder.local_get(flag);
+                builder.unop(walrus::ir::UnaryOp::I32Eqz);
+                builder.if_else(None, |then| {
+                    then.unreachable();
+                }, |_| {});
             }
-            Mir::Block(ops) => {
-                builder.block(ValType::I64, |block| {
-                    for op in ops {
-                        mir_to_wasm(op, context.clone()).expect("Failed to compile instruction");
-                    }
+            builder.local_get(local);
+        }
+        Mir::LocalSet(index) => {
+            let local = context.get_or_add_local(*index, ValType::I64);
+            builder.local_set(local);
+            mark_initialized(*index, builder, context);
+        }
+        Mir::LocalTee(index) => {
+            let local = context.get_or_add_local(*index, ValType::I64);
+            builder.local_tee(local);
+            mark_initialized(*index, builder, context);
+        }
+        Mir::Block(ops) => {
+            let mut result = Ok(());
+            builder.block(ValType::I64, |block| {
+                result = mir_seq_to_wasm(ops, block, context);
+            });
+            result?;
+        }
+        Mir::Loop(ops) => {
+            // The loop lives inside a block so a break can leave it with a value
+            let mut result = Ok(());
+            builder.block(ValType::I64, |exit| {
+                let exit_id = exit.id();
+                exit.loop_(None, |head| {
+                    let head_id = head.id();
+                    count_step(head, context);
+                    context.loop_labels.push((exit_id, head_id));
+                    result = mir_seq_to_wasm(ops, head, context);
+                    context.loop_labels.pop();
+                    head.br(head_id);
                 });
+                exit.unreachable();
+            });
+            result?;
+        }
+        Mir::Break => {
+            let (exit_id, _) = context.loop_labels.last().copied().wrap_err("Break outside of a loop")?;
+            builder.br(exit_id);
+        }
+        Mir::Continue => {
+            let (_, head_id) = context.loop_labels.last().copied().wrap_err("Continue outside of a loop")?;
+            builder.br(head_id);
+        }
+        Mir::Return => {
+            builder.return_();
+        }
+        Mir::IfElse(then_ops, else_ops) => {
+            builder.unop(walrus::ir::UnaryOp::I32WrapI64);
+            if_else_to_wasm(then_ops, else_ops.as_deref(), builder, context)?;
+        }
+        Mir::Drop => {
+            builder.drop();
+        }
+        Mir::PrintI64 => {
+            let print = context.get_or_add_import("env", "print_i64", &[ValType::I64], &[ValType::I64]);
+            builder.call(print);
+        }
+        Mir::AllocArray(len) => {
+            let memory = context.memory;
+            let heap_pointer = context.heap_pointer;
+            let end = context.add_scratch_local(ValType::I32);
+            builder.global_get(heap_pointer);
+            builder.i32_const(((*len + 1) * 8) as i32);
+            builder.binop(walrus::ir::BinaryOp::I32Add);
+            builder.local_tee(end);
+            grow_memory_to(end, memory, builder);
+            builder.global_get(heap_pointer);
+            builder.i64_const(*len as i64);
+            builder.store(memory, StoreKind::I64 { atomic: false }, MemArg { align: 8, offset: 0 });
+            builder.global_get(heap_pointer);
+            builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            builder.local_get(end);
+            builder.global_set(heap_pointer);
+        }
+        Mir::ArrayGet => {
+            let index = context.add_scratch_local(ValType::I64);
+            let array = context.add_scratch_local(ValType::I64);
+            builder.local_set(index);
+            builder.local_set(array);
+            array_element_address(array, index, builder, context);
+            builder.load(context.memory, LoadKind::I64 { atomic: false }, MemArg { align: 8, offset: 8 });
+        }
+        Mir::ArraySet => {
+            let value = context.add_scratch_local(ValType::I64);
+            let index = context.add_scratch_local(ValType::I64);
+            let array = context.add_scratch_local(ValType::I64);
+            builder.local_set(value);
+            builder.local_set(index);
+            builder.local_set(array);
+            array_element_address(array, index, builder, context);
+            builder.local_get(value);
+            builder.store(context.memory, StoreKind::I64 { atomic: false }, MemArg { align: 8, offset: 8 });
+        }
+        Mir::ArrayEqual => {
+            let rhs = context.add_scratch_local(ValType::I64);
+            let lhs = context.add_scratch_local(ValType::I64);
+            let index = context.add_scratch_local(ValType::I64);
+            let memory = context.memory;
+            builder.local_set(rhs);
+            builder.local_set(lhs);
+            fn length(array: LocalId, memory: walrus::MemoryId, builder: &mut InstrSeqBuilder) {
+                builder.local_get(array);
+                builder.unop(walrus::ir::UnaryOp::I32WrapI64);
+                builder.load(memory, LoadKind::I64 { atomic: false }, MemArg { align: 8, offset: 0 });
             }
-            Mir::IfElse(then_ops, else_ops) => {
+            // Elements are compared as the i64s they are stored as, so nested arrays compare by address
+            fn element(array: LocalId, index: LocalId, memory: walrus::MemoryId, builder: &mut InstrSeqBuilder) {
+                builder.local_get(array);
+                builder.local_get(index);
+                builder.i64_const(8);
+                builder.binop(walrus::ir::BinaryOp::I64Mul);
+                builder.binop(walrus::ir::BinaryOp::I64Add);
                 builder.unop(walrus::ir::UnaryOp::I32WrapI64);
-
-                if let Some(else_ops) = else_ops {
-                    builder.if_else(ValType::I64, |then| {
-                        for op in then_ops {
-                            mir_to_wasm(op, context.clone()).expect("Failed to compile instruction");
-                        }
-                    }, |else_builder| {
-                        for op in else_ops {
-                            mir_to_wasm(op, context.clone()).expect("Failed to compile instruction");
-                        }
-                    });
-                } else {}
+                builder.load(memory, LoadKind::I64 { atomic: false }, MemArg { align: 8, offset: 8 });
             }
-            _ => unimplemented!("Unsupported instruction: {:?}", op),
+            builder.block(ValType::I64, |done| {
+                let done_id = done.id();
+                // Arrays of different lengths are never equal
+                done.i64_const(0);
+                length(lhs, memory, done);
+                length(rhs, memory, done);
+                done.binop(walrus::ir::BinaryOp::I64Ne);
+                done.br_if(done_id);
+                done.drop();
+                done.i64_const(0);
+                done.local_set(index);
+                done.loop_(None, |head| {
+                    let head_id = head.id();
+                    // Equal once every element matched
+                    head.i64_const(1);
+                    head.local_get(index);
+                    length(lhs, memory, head);
+                    head.binop(walrus::ir::BinaryOp::I64GeU);
+                    head.br_if(done_id);
+                    head.drop();
+                    head.i64_const(0);
+                    element(lhs, index, memory, head);
+                    element(rhs, index, memory, head);
+                    head.binop(walrus::ir::BinaryOp::I64Ne);
+                    head.br_if(done_id);
+                    head.drop();
+                    head.local_get(index);
+                    head.i64_const(1);
+                    head.binop(walrus::ir::BinaryOp::I64Add);
+                    head.local_set(index);
+                    head.br(head_id);
+                });
+                done.unreachable();
+            });
+        }
+        Mir::ArraySlice => {
+            let memory = context.memory;
+            let heap_pointer = context.heap_pointer;
+            let end = context.add_scratch_local(ValType::I64);
+            let start = context.add_scratch_local(ValType::I64);
+            let array = context.add_scratch_local(ValType::I64);
+            let length = context.add_scratch_local(ValType::I64);
+            let slice = context.add_scratch_local(ValType::I64);
+            let offset = context.add_scratch_local(ValType::I64);
+            let heap_end = context.add_scratch_local(ValType::I32);
+            let out_of_order = context.get_or_add_import("env", "slice_out_of_order", &[], &[]);
+            builder.local_set(end);
+            builder.local_set(start);
+            builder.local_tee(array);
+            builder.unop(walrus::ir::UnaryOp::I32WrapI64);
+            builder.load(memory, LoadKind::I64 { atomic: false }, MemArg { align: 8, offset: 0 });
+            builder.local_set(length);
+            clamp_slice_index(start, length, builder);
+            clamp_slice_index(end, length, builder);
+            builder.local_get(start);
+            builder.local_get(end);
+            builder.binop(walrus::ir::BinaryOp::I64GtS);
+            builder.if_else(None, |then| {
+                then.call(out_of_order);
+                then.unreachable();
+            }, |_| {});
+
+            // Allocate the new array, its length is the difference of the indices
+            builder.local_get(end);
+            builder.local_get(start);
+            builder.binop(walrus::ir::BinaryOp::I64Sub);
+            builder.local_set(length);
+            builder.global_get(heap_pointer);
+            builder.unop(walrus::ir::UnaryOp::I64ExtendUI32);
+            builder.local_set(slice);
+            builder.global_get(heap_pointer);
+            builder.local_get(length);
+            builder.i64_const(1);
+            builder.binop(walrus::ir::BinaryOp::I64Add);
+            builder.i64_const(8);
+            builder.binop(walrus::ir::BinaryOp::I64Mul);
+            builder.unop(walrus::ir::UnaryOp::I32WrapI64);
+            builder.binop(walrus::ir::BinaryOp::I32Add);
+            builder.local_tee(heap_end);
+            grow_memory_to(heap_end, memory, builder);
+            builder.local_get(slice);
+            builder.unop(walrus::ir::UnaryOp::I32WrapI64);
+            builder.local_get(length);
+            builder.store(memory, StoreKind::I64 { atomic: false }, MemArg { align: 8, offset: 0 });
+            builder.local_get(heap_end);
+            builder.global_set(heap_pointer);
+
+            // Copy the elements one by one, `offset` counts bytes from the first copied element
+            builder.local_get(start);
+            builder.i64_const(8);
+            builder.binop(walrus::ir::BinaryOp::I64Mul);
+            builder.local_get(array);
+            builder.binop(walrus::ir::BinaryOp::I64Add);
+            builder.local_set(array);
+            builder.i64_const(0);
+            builder.local_set(offset);
+            builder.block(None, |done| {
+                let done_id = done.id();
+                done.loop_(None, |head| {
+                    let head_id = head.id();
+                    head.local_get(offset);
+                    head.local_get(length);
+                    head.i64_const(8);
+                    head.binop(walrus::ir::BinaryOp::I64Mul);
+                    head.binop(walrus::ir::BinaryOp::I64GeU);
+                    head.br_if(done_id);
+                    head.local_get(slice);
+                    head.local_get(offset);
+                    head.binop(walrus::ir::BinaryOp::I64Add);
+                    head.unop(walrus::ir::UnaryOp::I32WrapI64);
+                    head.local_get(array);
+                    head.local_get(offset);
+                    head.binop(walrus::ir::BinaryOp::I64Add);
+                    head.unop(walrus::ir::UnaryOp::I32WrapI64);
+                    head.load(memory, LoadKind::I64 { atomic: false }, MemArg { align: 8, offset: 8 });
+                    head.store(memory, StoreKind::I64 { atomic: false }, MemArg { align: 8, offset: 8 });
+                    head.local_get(offset);
+                    head.i64_const(8);
+                    head.binop(walrus::ir::BinaryOp::I64Add);
+                    head.local_set(offset);
+                    head.br(head_id);
+                });
+            });
+            builder.local_get(slice);
         }
+        Mir::Nop => return Err(eyre::eyre!("Nop reached the lowering, run remove_nops first")),
+    }
 
-        Ok(())
-    })?;
+    Ok(())
+}
+/// Add, subtract or multiply the two values on top of the stack, handling overflow as the options ask.
+fn arithmetic(op: &Mir, builder: &mut InstrSeqBuilder, context: &mut MirContext) {
+    let binop = match op {
+        Mir::AddI64 => walrus::ir::BinaryOp::I64Add,
+        Mir::SubI64 => walrus::ir::BinaryOp::I64Sub,
+        _ => walrus::ir::BinaryOp::I64Mul,
+    };
+    if context.options.overflow == Overflow::Wrap {
+        builder.binop(binop);
+        return;
+    }
+    let rhs = context.add_scratch_local(ValType::I64);
+    let lhs = context.add_scratch_local(ValType::I64);
+    let result = context.add_scratch_local(ValType::I64);
+    builder.local_set(rhs);
+    builder.local_tee(lhs);
+    builder.local_get(rhs);
+    builder.binop(binop);
+    builder.local_set(result);
+
+    // Leaves whether the wrapped result overflowed, as an i32
+    match op {
+        Mir::AddI64 | Mir::SubI64 => {
+            // A sum overflowed when both operands differ in sign from it, a difference when the operands differ in
+            // sign from each other and the left one from the result
+            builder.local_get(lhs);
+            builder.local_get(if *op == Mir::AddI64 { result } else { rhs });
+            builder.binop(walrus::ir::BinaryOp::I64Xor);
+            builder.local_get(if *op == Mir::AddI64 { rhs } else { lhs });
+            builder.local_get(result);
+            builder.binop(walrus::ir::BinaryOp::I64Xor);
+            builder.binop(walrus::ir::BinaryOp::I64And);
+            builder.i64_const(0);
+            builder.binop(walrus::ir::BinaryOp::I64LtS);
+        }
+        _ => {
+            // The wrapped product divided back differs from the other factor exactly when it overflowed,
+            // dividing by zero or i64::MIN by -1 would trap on its own so those are decided first
+            builder.local_get(rhs);
+            builder.unop(walrus::ir::UnaryOp::I64Eqz);
+            builder.if_else(ValType::I32, |zero| {
+                zero.i32_const(0);
+            }, |nonzero| {
+                nonzero.local_get(rhs);
+                nonzero.i64_const(-1);
+                nonzero.binop(walrus::ir::BinaryOp::I64Eq);
+                nonzero.if_else(ValType::I32, |negate| {
+                    negate.local_get(lhs);
+                    negate.i64_const(i64::MIN);
+                    negate.binop(walrus::ir::BinaryOp::I64Eq);
+                }, |divide| {
+                    divide.local_get(result);
+                    divide.local_get(rhs);
+                    divide.binop(walrus::ir::BinaryOp::I64DivS);
+                    divide.local_get(lhs);
+                    divide.binop(walrus::ir::BinaryOp::I64Ne);
+                });
+            });
+        }
+    }
 
+    if context.options.overflow == Overflow::Checked {
+        let overflow = context.get_or_add_import("env", "integer_overflow", &[], &[]);
+        builder.if_else(None, |then| {
+            then.call(overflow);
+            then.unreachable();
+        }, |_| {});
+        builder.local_get(result);
+        return;
+    }
+    // Saturates towards the sign the exact result has, that of the left operand for a sum or difference and
+    // that of both factors combined for a product
+    builder.if_else(ValType::I64, |then| {
+        then.local_get(lhs);
+        if *op == Mir::MulI64 {
+            then.local_get(rhs);
+            then.binop(walrus::ir::BinaryOp::I64Xor);
+        }
+        then.i64_const(63);
+        then.binop(walrus::ir::BinaryOp::I64ShrS);
+        then.i64_const(i64::MAX);
+        then.binop(walrus::ir::BinaryOp::I64Xor);
+    }, |else_| {
+        else_.local_get(result);
+    });
+}
+/// Multiply two locals into `target`, calling the `overflow` import when the product does not fit in an i64.
+fn checked_mul(lhs: LocalId, rhs: LocalId, target: LocalId, product: LocalId, overflow: walrus::FunctionId, builder: &mut InstrSeqBuilder) {
+    builder.local_get(lhs);
+    builder.local_get(rhs);
+    builder.binop(walrus::ir::BinaryOp::I64Mul);
+    builder.local_set(product);
+    // The wrapped product divided back differs from the other factor exactly when it overflowed,
+    // dividing i64::MIN by -1 would trap on its own so that case is checked first
+    builder.local_get(rhs);
+    builder.i64_const(-1);
+    builder.binop(walrus::ir::BinaryOp::I64Eq);
+    builder.local_get(lhs);
+    builder.i64_const(i64::MIN);
+    builder.binop(walrus::ir::BinaryOp::I64Eq);
+    builder.binop(walrus::ir::BinaryOp::I32And);
+    builder.if_else(None, |then| {
+        then.call(overflow);
+        then.unreachable();
+    }, |_| {});
+    builder.local_get(rhs);
+    builder.unop(walrus::ir::UnaryOp::I64Eqz);
+    builder.if_else(None, |_| {}, |nonzero| {
+        nonzero.local_get(product);
+        nonzero.local_get(rhs);
+        nonzero.binop(walrus::ir::BinaryOp::I64DivS);
+        nonzero.local_get(lhs);
+        nonzero.binop(walrus::ir::BinaryOp::I64Ne);
+        nonzero.if_else(None, |then| {
+            then.call(overflow);
+            then.unreachable();
+        }, |_| {});
+    });
+    builder.local_get(product);
+    builder.local_set(target);
+}
+/// Grow the memory by the missing pages when it ends before the i32 address on top of the stack, trapping when it
+/// cannot grow.
+///
+/// The address is also read from `end`, where the caller keeps it.
+fn grow_memory_to(end: LocalId, memory: walrus::MemoryId, builder: &mut InstrSeqBuilder) {
+    builder.memory_size(memory);
+    builder.i32_const(16);
+    builder.binop(walrus::ir::BinaryOp::I32Shl);
+    builder.binop(walrus::ir::BinaryOp::I32GtU);
+    builder.if_else(None, |grow| {
+        grow.local_get(end);
+        grow.i32_const(0xFFFF);
+        grow.binop(walrus::ir::BinaryOp::I32Add);
+        grow.i32_const(16);
+        grow.binop(walrus::ir::BinaryOp::I32ShrU);
+        grow.memory_size(memory);
+        grow.binop(walrus::ir::BinaryOp::I32Sub);
+        grow.memory_grow(memory);
+        grow.i32_const(-1);
+        grow.binop(walrus::ir::BinaryOp::I32Eq);
+        grow.if_else(None, |fail| {
+            fail.unreachable();
+        }, |_| {});
+    }, |_| {});
+}
+/// Resolve the index in the local against the array length, counting negative ones from the end and clamping to the array.
+fn clamp_slice_index(index: LocalId, length: LocalId, builder: &mut InstrSeqBuilder) {
+    builder.local_get(index);
+    builder.i64_const(0);
+    builder.binop(walrus::ir::BinaryOp::I64LtS);
+    builder.if_else(ValType::I64, |negative| {
+        negative.local_get(index);
+        negative.local_get(length);
+        negative.binop(walrus::ir::BinaryOp::I64Add);
+    }, |positive| {
+        positive.local_get(index);
+    });
+    builder.local_set(index);
+    // The larger of the index and 0, then the smaller of that and the length
+    builder.local_get(index);
+    builder.i64_const(0);
+    builder.local_get(index);
+    builder.i64_const(0);
+    builder.binop(walrus::ir::BinaryOp::I64GtS);
+    builder.select(None);
+    builder.local_tee(index);
+    builder.local_get(length);
+    builder.local_get(index);
+    builder.local_get(length);
+    builder.binop(walrus::ir::BinaryOp::I64LtS);
+    builder.select(None);
+    builder.local_set(index);
+}
+/// Leave the address of an array's element, relative to the elements, trapping when the index is out of bounds.
+fn array_element_address(array: LocalId, index: LocalId, builder: &mut InstrSeqBuilder, context: &mut MirContext) {
+    // Comparing unsigned also catches negative indices
+    builder.local_get(index);
+    builder.local_get(array);
+    builder.unop(walrus::ir::UnaryOp::I32WrapI64);
+    builder.load(context.memory, LoadKind::I64 { atomic: false }, MemArg { align: 8, offset: 0 });
+    builder.binop(walrus::ir::BinaryOp::I64GeU);
+    builder.if_else(None, |then| {
+        then.unreachable();
+    }, |_| {});
+    builder.local_get(array);
+    builder.local_get(index);
+    builder.i64_const(8);
+    builder.binop(walrus::ir::BinaryOp::I64Mul);
+    builder.binop(walrus::ir::BinaryOp::I64Add);
+    builder.unop(walrus::ir::UnaryOp::I32WrapI64);
+}
+/// Take a step off the budget at the head of a loop, calling the host to abort once it is used up.
+fn count_step(builder: &mut InstrSeqBuilder, context: &mut MirContext) {
+    let Some(max_steps) = context.options.max_steps else {
+        return;
+    };
+    let steps = context.get_or_add_step_counter(max_steps);
+    let exceeded = context.get_or_add_import("env", "step_limit_exceeded", &[], &[]);
+    builder.global_get(steps);
+    builder.unop(walrus::ir::UnaryOp::I64Eqz);
+    builder.if_else(None, |then| {
+        then.call(exceeded);
+        then.unreachable();
+    }, |_| {});
+    builder.global_get(steps);
+    builder.i64_const(1);
+    builder.binop(walrus::ir::BinaryOp::I64Sub);
+    builder.global_set(steps);
+}
+/// Raise the initialization flag of a local after it is assigned in strict mode.
+fn mark_initialized(index: u32, builder: &mut InstrSeqBuilder, context: &mut MirContext) {
+    if context.options.strict_locals {
+        let flag = context.get_or_add_init_flag(index);
+        builder.i32_const(1);
+        builder.local_set(flag);
+    }
+}
+/// Lower a sequence of instructions into the given instruction sequence.
+pub fn mir_seq_to_wasm(ops: &[Mir], builder: &mut InstrSeqBuilder, context: &mut MirContext) -> Result<()> {
+    let mut ops = ops.iter().peekable();
+    while let Some(op) = ops.next() {
+        // A comparison deciding an `if` stays the i32 the branch takes, instead of being extended to an i64 and
+        // wrapped right back
+        if let (Some(binop), Some(Mir::IfElse(then_ops, else_ops))) = (comparison_binop(op), ops.peek()) {
+            builder.binop(binop);
+            if_else_to_wasm(then_ops, else_ops.as_deref(), builder, context)?;
+            ops.next();
+            continue;
+        }
+        mir_to_wasm(op, builder, context)?;
+    }
     Ok(())
 }
-pub fn to_wat_module(bytecode: &[Mir]) -> Vec<u8> {
+/// The Wasm comparison of a comparison instruction, leaving an i32.
+fn comparison_binop(op: &Mir) -> Option<walrus::ir::BinaryOp> {
+    match op {
+        Mir::GreaterThanI64 => Some(walrus::ir::BinaryOp::I64GtS),
+        Mir::LessThanI64 => Some(walrus::ir::BinaryOp::I64LtS),
+        Mir::EqualI64 => Some(walrus::ir::BinaryOp::I64Eq),
+        Mir::NotEqualI64 => Some(walrus::ir::BinaryOp::I64Ne),
+        Mir::GreaterThanOrEqualI64 => Some(walrus::ir::BinaryOp::I64GeS),
+        Mir::LessThanOrEqualI64 => Some(walrus::ir::BinaryOp::I64LeS),
+        _ => None,
+    }
+}
+/// Lower the arms of an `if` whose i32 condition is on top of the stack.
+fn if_else_to_wasm(then_ops: &[Mir], else_ops: Option<&[Mir]>, builder: &mut InstrSeqBuilder, context: &mut MirContext) -> Result<()> {
+    if let Some(else_ops) = else_ops {
+        // Both arms need the context, so build them as dangling sequences one after another
+        let consequent = {
+            let mut then_builder = builder.dangling_instr_seq(ValType::I64);
+            mir_seq_to_wasm(then_ops, &mut then_builder, context)?;
+            then_builder.id()
+        };
+        let alternative = {
+            let mut else_builder = builder.dangling_instr_seq(ValType::I64);
+            mir_seq_to_wasm(else_ops, &mut else_builder, context)?;
+            else_builder.id()
+        };
+        builder.instr(walrus::ir::IfElse { consequent, alternative });
+    } else {
+        let consequent = {
+            let mut then_builder = builder.dangling_instr_seq(None);
+            mir_seq_to_wasm(then_ops, &mut then_builder, context)?;
+            then_builder.id()
+        };
+        let alternative = builder.dangling_instr_seq(None).id();
+        builder.instr(walrus::ir::IfElse { consequent, alternative });
+    }
+    Ok(())
+}
+/// Render MIR as WAT-like pseudocode, staying at the MIR level instead of the lowered Wasm.
+pub fn to_pseudo_wat(bytecode: &[Mir]) -> String {
+    let mut out = String::new();
+    write_pseudo_wat(bytecode, 0, &mut out);
+    out
+}
+fn write_pseudo_wat(bytecode: &[Mir], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    for op in bytecode {
+        let line = match op {
+            Mir::ConstI64(num) => format!("i64.const {}", num),
+            Mir::AddI64 => "i64.add".to_string(),
+            Mir::SubI64 => "i64.sub".to_string(),
+            Mir::MulI64 => "i64.mul".to_string(),
+            Mir::DivI64 => "i64.div_s".to_string(),
+            Mir::ModI64 => "i64.rem_s".to_string(),
+            Mir::AndI64 => "i64.and".to_string(),
+            Mir::OrI64 => "i64.or".to_string(),
+            Mir::XorI64 => "i64.xor".to_string(),
+            Mir::ShlI64 => "i64.shl".to_string(),
+            Mir::ShrI64 => "i64.shr_s".to_string(),
+            Mir::GreaterThanI64 => "i64.gt_s".to_string(),
+            Mir::LessThanI64 => "i64.lt_s".to_string(),
+            Mir::EqualI64 => "i64.eq".to_string(),
+            Mir::NotEqualI64 => "i64.ne".to_string(),
+            Mir::GreaterThanOrEqualI64 => "i64.ge_s".to_string(),
+            Mir::LessThanOrEqualI64 => "i64.le_s".to_string(),
+            Mir::AbsI64 => "i64.abs".to_string(),
+            Mir::MinI64 => "i64.min_s".to_string(),
+            Mir::MaxI64 => "i64.max_s".to_string(),
+            Mir::PowI64 => "i64.pow_s".to_string(),
+            Mir::LocalGet(index) => format!("local.get ${}", index),
+            Mir::LocalSet(index) => format!("local.set ${}", index),
+            Mir::LocalTee(index) => format!("local.tee ${}", index),
+            Mir::Break => "break".to_string(),
+            Mir::Continue => "continue".to_string(),
+            Mir::Return => "return".to_string(),
+            Mir::Drop => "drop".to_string(),
+            Mir::PrintI64 => "call $print_i64".to_string(),
+            Mir::AllocArray(len) => format!("array.alloc {}", len),
+            Mir::ArrayGet => "array.get".to_string(),
+            Mir::ArraySet => "array.set".to_string(),
+            Mir::ArrayEqual => "array.eq".to_string(),
+            Mir::ArraySlice => "array.slice".to_string(),
+            Mir::Nop => "nop".to_string(),
+            Mir::Block(ops) => {
+                out.push_str(&format!("{}block\n", indent));
+                write_pseudo_wat(ops, depth + 1, out);
+                "end".to_string()
+            }
+            Mir::Loop(ops) => {
+                out.push_str(&format!("{}loop\n", indent));
+                write_pseudo_wat(ops, depth + 1, out);
+                "end".to_string()
+            }
+            Mir::IfElse(then_ops, else_ops) => {
+                out.push_str(&format!("{}if\n", indent));
+                write_pseudo_wat(then_ops, depth + 1, out);
+                if let Some(else_ops) = else_ops {
+                    out.push_str(&format!("{}else\n", indent));
+                    write_pseudo_wat(else_ops, depth + 1, out);
+                }
+                "end".to_string()
+            }
+        };
+        out.push_str(&format!("{}{}\n", indent, line));
+    }
+}
+
+pub fn to_wat_module(bytecode: &[Mir], options: WasmOptions) -> Result<Vec<u8>> {
     let config = walrus::ModuleConfig::new();
     let module = walrus::Module::with_config(config);
-    let context = MirContext::new(module);
-    context.borrow_mut().set_new_builder(&[], &[walrus::ValType::I64]);
+    let context = MirContext::new(module, options);
+    let param_types = vec![ValType::I64; options.params as usize];
+    context.borrow_mut().set_new_builder(&param_types, &[walrus::ValType::I64]);
+    let arguments = (0..options.params).map(|index| context.borrow_mut().add_local(index, ValType::I64)).collect();
 
-    for instr in bytecode {
-        mir_to_wasm(instr, context.clone()).expect("Failed to compile instruction");
-    }
+    context.borrow_mut().function_body(|builder, context| {
+        // Parameters are assigned by the caller
+        for index in 0..context.options.params {
+            mark_initialized(index, builder, context);
+        }
+        mir_seq_to_wasm(bytecode, builder, context)
+    }).map_err(|err| err.wrap_err("Failed to compile instruction"))?;
 
-    let function = context.borrow_mut().finish_builder(vec![]).expect("Failed to finish builder");
+    let function = context.borrow_mut().finish_builder(arguments)?;
     context.borrow_mut().export_function("main", function);
+    context.borrow_mut().export_memory("memory");
 
     let mut context = context.borrow_mut();
-    context.emit_wasm()
+    Ok(context.emit_wasm())
+}
+pub fn to_wasm_module(bytecode: &[Mir], options: WasmOptions) -> Result<Vec<u8>> {
+    to_wat_module(bytecode, options)
+}
+/// Lower the bytecode and render the resulting module in the WebAssembly text format.
+pub fn to_wat_text(bytecode: &[Mir], options: WasmOptions) -> Result<String> {
+    let wasm = to_wat_module(bytecode, options)?;
+    wasmprinter::print_bytes(&wasm).map_err(|err| eyre::eyre!("Failed to print Wasm: {:#?}", err))
 }
-pub fn to_wasm_module(bytecode: &[Mir]) -> Result<Vec<u8>> {
-    let wasm = to_wat_module(bytecode);
-    let wat = wasmprinter::print_bytes(&wasm).map_err(|err| eyre::eyre!("Failed to print Wasm: {:#?}", err))?;
-    println!("===== Wasm: {} bytes", wasm.len());
-    println!("{}", wat);
-
-    Ok(wasm)
-}
\ No newline at end of file
@@ -2,28 +2,56 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::rc::Rc;
-use walrus::{FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, Module, ValType};
+use walrus::{FunctionBuilder, FunctionId, GlobalId, InitExpr, InstrSeqBuilder, LocalId, MemoryId, Module, ValType};
+use walrus::ir::InstrSeqId;
 use eyre::{ContextCompat, OptionExt, Result};
+use crate::mir::WasmOptions;
 
 pub type MirSharedContext = Rc<RefCell<MirContext>>;
+/// State of lowering MIR into a walrus module.
+///
+/// The hash maps are only ever used for lookups, locals and imports are added to the module in the order
+/// the MIR first uses them, so the same MIR always lowers to the same bytes.
 pub struct MirContext {
     pub module: Module,
     pub builder: Option<FunctionBuilder>,
-    pub locals_hash: HashMap<u32, LocalId>
+    pub locals_hash: HashMap<u32, LocalId>,
+    pub init_flags_hash: HashMap<u32, LocalId>,
+    pub imports_hash: HashMap<String, FunctionId>,
+    /// Exit block and header of every loop being lowered, innermost last.
+    pub loop_labels: Vec<(InstrSeqId, InstrSeqId)>,
+    /// Linear memory holding the arrays.
+    pub memory: MemoryId,
+    /// Address of the first free byte of `memory`, bumped by every allocation.
+    pub heap_pointer: GlobalId,
+    /// Loop iterations left before the step limit is exceeded, only present with a limit.
+    pub step_counter: Option<GlobalId>,
+    pub options: WasmOptions,
 }
 impl Debug for MirContext {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("MirContext")
             .field("builder", &self.builder)
+            .field("options", &self.options)
             .finish()
     }
 }
 impl MirContext {
-    pub fn new(module: Module) -> MirSharedContext {
+    pub fn new(mut module: Module, options: WasmOptions) -> MirSharedContext {
+        let memory = module.memories.add_local(false, 1, None);
+        // The first word stays unused so no array lives at the null address 0
+        let heap_pointer = module.globals.add_local(ValType::I32, true, InitExpr::Value(walrus::ir::Value::I32(8)));
         Rc::new(RefCell::new(MirContext {
             module,
             builder: None,
-            locals_hash: HashMap::new()
+            locals_hash: HashMap::new(),
+            init_flags_hash: HashMap::new(),
+            imports_hash: HashMap::new(),
+            loop_labels: Vec::new(),
+            memory,
+            heap_pointer,
+            step_counter: None,
+            options,
         }))
     }
 
@@ -44,6 +72,34 @@ impl MirContext {
             self.get_local(index).expect("Local not found")
         }
     }
+    /// Adds a local not tied to any MIR index, for values the lowering juggles itself.
+    pub fn add_scratch_local(&mut self, val_type: ValType) -> LocalId {
+        self.module.locals.add(val_type)
+    }
+    pub fn get_or_add_init_flag(&mut self, index: u32) -> LocalId {
+        let locals = &mut self.module.locals;
+        *self.init_flags_hash.entry(index).or_insert_with(|| locals.add(ValType::I32))
+    }
+
+    // Globals
+    pub fn get_or_add_step_counter(&mut self, max_steps: u64) -> GlobalId {
+        let globals = &mut self.module.globals;
+        *self.step_counter.get_or_insert_with(|| {
+            globals.add_local(ValType::I64, true, InitExpr::Value(walrus::ir::Value::I64(max_steps as i64)))
+        })
+    }
+
+    // Imports
+    pub fn get_or_add_import(&mut self, module: &str, name: &str, params: &[ValType], results: &[ValType]) -> FunctionId {
+        let key = format!("{}.{}", module, name);
+        if let Some(id) = self.imports_hash.get(&key) {
+            return *id;
+        }
+        let ty = self.module.types.add(params, results);
+        let (id, _) = self.module.add_import_func(module, name, ty);
+        self.imports_hash.insert(key, id);
+        id
+    }
 
     // Function builder
     pub fn set_builder(&mut self, builder: FunctionBuilder) {
@@ -67,18 +123,22 @@ impl MirContext {
     // Sequence builder
     pub fn function_body<F>(&mut self, f: F) -> Result<()>
     where
-        F: FnOnce(InstrSeqBuilder) -> Result<()>
+        F: FnOnce(&mut InstrSeqBuilder, &mut MirContext) -> Result<()>
     {
-        let builder = self.get_builder().ok_or_eyre("Builder not set")?;
-        let seq = builder.func_body();
-        f(seq)?;
-        Ok(())
+        // Take the builder out so the body can be built while the rest of the context is borrowed
+        let mut builder = self.builder.take().ok_or_eyre("Builder not set")?;
+        let result = f(&mut builder.func_body(), self);
+        self.builder = Some(builder);
+        result
     }
 
     // Export
     pub fn export_function(&mut self, name: &str, function: FunctionId) {
         self.module.exports.add(name, function);
     }
+    pub fn export_memory(&mut self, name: &str) {
+        self.module.exports.add(name, self.memory);
+    }
 
     // Emit
     pub fn emit_wasm(&mut self) -> Vec<u8> {
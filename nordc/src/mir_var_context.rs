@@ -1,10 +1,86 @@
-use std::cell::RefCell;
 use std::collections::HashMap;
-use std::rc::Rc;
 
-pub type MirSharedVarContext = Rc<RefCell<MirVarContext>>;
+use crate::ast::Span;
+use crate::mir::ValueType;
 
+/// Variables visible while compiling, in nested lexical scopes.
 pub struct MirVarContext {
-    parent: Option<MirSharedVarContext>,
-    vars: Vec<HashMap<String, usize>>,
-}
\ No newline at end of file
+    /// Scopes from the outermost to the innermost, mapping names to local indices.
+    vars: Vec<HashMap<String, u32>>,
+    /// Type of every local by index, hidden ones are integers.
+    types: Vec<ValueType>,
+    /// Type and span of every `return` compiled so far, unit for those without a value.
+    returns: Vec<(ValueType, Span)>,
+    /// Number of loops around the expression being compiled.
+    loop_depth: u32,
+}
+impl MirVarContext {
+    pub fn new() -> Self {
+        MirVarContext {
+            vars: vec![HashMap::new()],
+            types: Vec::new(),
+            returns: Vec::new(),
+            loop_depth: 0,
+        }
+    }
+
+    // Scopes
+    pub fn push_scope(&mut self) {
+        self.vars.push(HashMap::new());
+    }
+    pub fn pop_scope(&mut self) {
+        // The outermost scope lives as long as the context
+        if self.vars.len() > 1 {
+            self.vars.pop();
+        }
+    }
+
+    // Variables
+    /// Binds the name to a fresh local of the given type in the innermost scope, shadowing any binding further out.
+    pub fn declare(&mut self, name: &str, value_type: ValueType) -> u32 {
+        let index = self.add_local(value_type);
+        self.vars.last_mut().expect("No scope").insert(name.to_string(), index);
+        index
+    }
+    /// Allocates an integer local no name refers to.
+    pub fn add_hidden(&mut self) -> u32 {
+        self.add_local(ValueType::Int)
+    }
+    /// Allocates a local, indices are never reused so the locals of popped scopes keep their own slots.
+    fn add_local(&mut self, value_type: ValueType) -> u32 {
+        self.types.push(value_type);
+        self.types.len() as u32 - 1
+    }
+    pub fn type_of(&self, index: u32) -> ValueType {
+        self.types.get(index as usize).copied().unwrap_or(ValueType::Int)
+    }
+    /// Looks the name up from the innermost scope outwards.
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.vars.iter().rev().find_map(|scope| scope.get(name).copied())
+    }
+
+    // Loops
+    pub fn enter_loop(&mut self) {
+        self.loop_depth += 1;
+    }
+    pub fn exit_loop(&mut self) {
+        self.loop_depth = self.loop_depth.saturating_sub(1);
+    }
+    /// Whether a `break` or `continue` has a loop to leave or repeat.
+    pub fn in_loop(&self) -> bool {
+        self.loop_depth > 0
+    }
+
+    // Returns
+    pub fn add_return(&mut self, value_type: ValueType, span: Span) {
+        self.returns.push((value_type, span));
+    }
+    pub fn returns(&self) -> &[(ValueType, Span)] {
+        &self.returns
+    }
+}
+impl Default for MirVarContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
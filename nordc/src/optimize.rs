@@ -0,0 +1,22 @@
+use eyre::{Result, WrapErr};
+use wasm_opt::OptimizationOptions;
+
+/// Runs Binaryen's optimizer over the module at a level from 0 to 4, level 0 returns it untouched.
+pub fn optimize(wasm: &[u8], level: u8) -> Result<Vec<u8>> {
+    let options = match level {
+        0 => return Ok(wasm.to_vec()),
+        1 => OptimizationOptions::new_opt_level_1(),
+        2 => OptimizationOptions::new_opt_level_2(),
+        3 => OptimizationOptions::new_opt_level_3(),
+        4 => OptimizationOptions::new_opt_level_4(),
+        _ => eyre::bail!("Optimization level must be between 0 and 4, got {}", level),
+    };
+
+    // wasm-opt works on files, so the module takes a round trip through a temporary directory
+    let dir = tempfile::tempdir().wrap_err("Failed to create a temporary directory")?;
+    let input = dir.path().join("input.wasm");
+    let output = dir.path().join("output.wasm");
+    std::fs::write(&input, wasm).wrap_err("Failed to write the module to optimize")?;
+    options.run(&input, &output).map_err(|err| eyre::eyre!("Failed to optimize: {:#?}", err))?;
+    std::fs::read(&output).wrap_err("Failed to read the optimized module")
+}
@@ -1,21 +1,112 @@
-use wasmtime::{Engine, Instance, Linker, Module, Store, WasmResults};
-use eyre::{ContextCompat, Result, WrapErr};
+use std::fmt::Write;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Module, Store, Trap, Val, ValType};
+use eyre::{ContextCompat, Result};
+
+/// Epoch deadline of runs without a timeout, far enough away to never be reached.
+const NO_DEADLINE: u64 = u64::MAX / 2;
+
+/// Host side state available to the imported functions.
+#[derive(Debug, Default)]
+pub struct HostState {
+    /// Output of `print`, collected here instead of written to stdout when set.
+    pub captured: Option<String>,
+}
+
+/// Results of an exported function that [`Runtime::run_named`] can convert the returned values into.
+pub trait WasmResults: Sized {
+    /// Converts the returned values, `None` when their number or types do not match.
+    fn from_values(values: &[Val]) -> Option<Self>;
+}
+impl WasmResults for () {
+    fn from_values(values: &[Val]) -> Option<Self> {
+        values.is_empty().then_some(())
+    }
+}
+impl WasmResults for i64 {
+    fn from_values(values: &[Val]) -> Option<Self> {
+        match values {
+            [value] => value.i64(),
+            _ => None,
+        }
+    }
+}
+impl WasmResults for i32 {
+    fn from_values(values: &[Val]) -> Option<Self> {
+        match values {
+            [value] => value.i32(),
+            _ => None,
+        }
+    }
+}
+impl WasmResults for (i64, i64) {
+    fn from_values(values: &[Val]) -> Option<Self> {
+        match values {
+            [first, second] => Some((first.i64()?, second.i64()?)),
+            _ => None,
+        }
+    }
+}
 
 pub struct Runtime {
     pub engine: Engine,
     pub module: Module,
-    pub linker: Linker<()>,
-    pub store: Store<()>,
+    pub linker: Linker<HostState>,
+    pub store: Store<HostState>,
     pub instance: Instance,
 }
 
 impl Runtime {
     pub fn new(bytes: &[u8]) -> Result<Self> {
-        let engine = Engine::default();
+        Self::with_state(bytes, HostState::default())
+    }
+    /// Creates a runtime collecting printed output, see [`Runtime::take_output`].
+    pub fn new_capturing(bytes: &[u8]) -> Result<Self> {
+        Self::with_state(bytes, HostState { captured: Some(String::new()) })
+    }
+    pub fn with_state(bytes: &[u8], state: HostState) -> Result<Self> {
+        let engine = Self::engine()?;
         let module = Module::new(&engine, bytes).map_err(|err| eyre::eyre!("Failed to create module: {:#?}", err))?;
+        Self::with_module(&engine, module, state)
+    }
+    /// Creates an engine configured like the one of every runtime, modules compiled with it can be passed to
+    /// [`Runtime::with_module`].
+    pub fn engine() -> Result<Engine> {
+        // Fuel and epochs are always checked so limits can be set per run,
+        // see `Runtime::run_with_fuel` and `Runtime::with_timeout`
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        Engine::new(&config).map_err(|err| eyre::eyre!("Failed to create engine: {:#?}", err))
+    }
+    /// Creates a runtime instantiating a module already compiled with `engine`, which must come from
+    /// [`Runtime::engine`].
+    pub fn with_module(engine: &Engine, module: Module, state: HostState) -> Result<Self> {
+        let engine = engine.clone();
         let mut linker = Linker::new(&engine);
-        let mut store = Store::new(&engine, ());
-        let instance = linker.instantiate(&mut store, &module).map_err(|err| eyre::eyre!("Failed to instantiate module: {:#?}", err))?;
+        // Host functions are registered unconditionally, modules not importing them just ignore them
+        linker.func_wrap("env", "print_i64", |mut caller: Caller<'_, HostState>, value: i64| {
+            match &mut caller.data_mut().captured {
+                Some(output) => writeln!(output, "{}", value).expect("Writing to a String cannot fail"),
+                None => println!("{}", value),
+            }
+            value
+        }).map_err(|err| eyre::eyre!("Failed to register host function: {:#?}", err))?;
+        linker.func_wrap("env", "step_limit_exceeded", || -> wasmtime::Result<()> {
+            Err(wasmtime::Error::msg("step limit exceeded"))
+        }).map_err(|err| eyre::eyre!("Failed to register host function: {:#?}", err))?;
+        linker.func_wrap("env", "integer_overflow", || -> wasmtime::Result<()> {
+            Err(wasmtime::Error::msg("integer overflow"))
+        }).map_err(|err| eyre::eyre!("Failed to register host function: {:#?}", err))?;
+        linker.func_wrap("env", "negative_exponent", || -> wasmtime::Result<()> {
+            Err(wasmtime::Error::msg("negative exponent of an integer power"))
+        }).map_err(|err| eyre::eyre!("Failed to register host function: {:#?}", err))?;
+        linker.func_wrap("env", "slice_out_of_order", || -> wasmtime::Result<()> {
+            Err(wasmtime::Error::msg("slice start is after its end"))
+        }).map_err(|err| eyre::eyre!("Failed to register host function: {:#?}", err))?;
+        let (store, instance) = Self::instantiate(&engine, &module, &linker, state)?;
 
         Ok(Self {
             engine,
@@ -26,9 +117,105 @@ impl Runtime {
         })
     }
 
-    pub fn run<T: WasmResults>(&mut self) -> Result<T> {
-        let main = self.instance.get_func(&mut self.store, "main").wrap_err("Failed to get function")?;
-        let answer = main.typed::<(), T>(&self.store).map_err(|err| eyre::eyre!("Failed to get typed function: {:#?}", err))?;
-        answer.call(&mut self.store, ()).map_err(|err| eyre::eyre!("Failed to call function: {:#?}", err))
+    /// Creates a store without limits and instantiates the module in it.
+    fn instantiate(engine: &Engine, module: &Module, linker: &Linker<HostState>, state: HostState) -> Result<(Store<HostState>, Instance)> {
+        let mut store = Store::new(engine, state);
+        store.set_fuel(u64::MAX).map_err(|err| eyre::eyre!("Failed to set fuel: {:#?}", err))?;
+        store.set_epoch_deadline(NO_DEADLINE);
+        let instance = linker.instantiate(&mut store, module).map_err(|err| eyre::eyre!("Failed to instantiate module: {:#?}", err))?;
+        Ok((store, instance))
+    }
+    /// Replaces the store with a fresh one and instantiates the module again, so the next run starts from clean
+    /// memory and globals.
+    ///
+    /// Captured output is discarded, a capturing runtime keeps capturing.
+    pub fn reset(&mut self) -> Result<()> {
+        let state = HostState {
+            captured: self.store.data().captured.as_ref().map(|_| String::new()),
+        };
+        let (store, instance) = Self::instantiate(&self.engine, &self.module, &self.linker, state)?;
+        self.store = store;
+        self.instance = instance;
+        Ok(())
+    }
+
+    /// Calls `main` with the given arguments, which must match its number of i64 parameters.
+    pub fn run(&mut self, args: &[i64]) -> Result<i64> {
+        self.run_named("main", args)
+    }
+    /// Calls the exported function of the given name, whose parameters must all be i64 and match the arguments.
+    pub fn run_named<T: WasmResults>(&mut self, name: &str, args: &[i64]) -> Result<T> {
+        let func = self.instance.get_func(&mut self.store, name).wrap_err_with(|| format!("No exported function `{}`", name))?;
+        let ty = func.ty(&self.store);
+        if ty.params().len() != args.len() {
+            eyre::bail!("`{}` takes {} argument(s) but {} were given", name, ty.params().len(), args.len());
+        }
+        if let Some(param) = ty.params().find(|param| !matches!(param, ValType::I64)) {
+            eyre::bail!("`{}` takes a parameter of type {} but only i64 arguments can be passed", name, param);
+        }
+        let params: Vec<Val> = args.iter().copied().map(Val::I64).collect();
+        // The placeholders are overwritten by the call
+        let mut results = vec![Val::I64(0); ty.results().len()];
+        func.call(&mut self.store, &params, &mut results).map_err(|err| match err.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => eyre::eyre!("Execution ran out of fuel"),
+            Some(Trap::Interrupt) => eyre::eyre!("Execution timed out"),
+            _ => eyre::eyre!("Failed to call function: {:#?}", err),
+        })?;
+        T::from_values(&results).wrap_err_with(|| format!("`{}` returned {:?}, which does not match the requested results", name, results))
+    }
+    /// Calls `main` like [`Runtime::run`], failing with an out of fuel error once it used up the given budget.
+    pub fn run_with_fuel(&mut self, args: &[i64], fuel: u64) -> Result<i64> {
+        self.store.set_fuel(fuel).map_err(|err| eyre::eyre!("Failed to set fuel: {:#?}", err))?;
+        let result = self.run(args);
+        // Later runs are unlimited again
+        self.store.set_fuel(u64::MAX).map_err(|err| eyre::eyre!("Failed to set fuel: {:#?}", err))?;
+        result
+    }
+    /// Calls `main` like [`Runtime::run`], failing with a timeout error once it ran for the given duration.
+    pub fn run_with_timeout(&mut self, args: &[i64], timeout: Duration) -> Result<i64> {
+        self.with_timeout(timeout, |runtime| runtime.run(args))
+    }
+    /// Runs `f` while a watchdog thread interrupts any Wasm code still running after `timeout`.
+    ///
+    /// The watchdog is stopped and joined before returning, also when `f` finishes early.
+    pub fn with_timeout<T>(&mut self, timeout: Duration, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let engine = self.engine.clone();
+        let watchdog = std::thread::spawn(move || {
+            // A disconnected channel means the run finished in time
+            if let Err(mpsc::RecvTimeoutError::Timeout) = stopped.recv_timeout(timeout) {
+                engine.increment_epoch();
+            }
+        });
+        self.store.set_epoch_deadline(1);
+        let result = f(self);
+        drop(stop);
+        watchdog.join().map_err(|_| eyre::eyre!("Timeout thread panicked"))?;
+        self.store.set_epoch_deadline(NO_DEADLINE);
+        result
+    }
+
+    /// Reads the elements of the array at the address a compiled program returned from the exported memory.
+    pub fn read_array(&mut self, address: i64) -> Result<Vec<i64>> {
+        if address == 0 {
+            eyre::bail!("Cannot read an array at the null address");
+        }
+        let memory = self.instance.get_memory(&mut self.store, "memory").wrap_err("The module exports no memory")?;
+        let data = memory.data(&self.store);
+        let read = |address: usize| -> Result<i64> {
+            let bytes = address.checked_add(8).and_then(|end| data.get(address..end))
+                .wrap_err_with(|| format!("Array reaches past the end of memory at {}", address))?;
+            Ok(i64::from_le_bytes(bytes.try_into().expect("Slice of 8 bytes")))
+        };
+        let address = usize::try_from(address).map_err(|_| eyre::eyre!("Invalid array address {}", address))?;
+        // Arrays are their length followed by the elements, all i64
+        let len = read(address)?;
+        let len = usize::try_from(len).map_err(|_| eyre::eyre!("Invalid array length {} at {}", len, address))?;
+        (0..len).map(|i| read(address + 8 + i * 8)).collect()
+    }
+
+    /// Takes the output printed so far when capturing, empty otherwise.
+    pub fn take_output(&mut self) -> String {
+        self.store.data_mut().captured.as_mut().map(std::mem::take).unwrap_or_default()
     }
 }
\ No newline at end of file
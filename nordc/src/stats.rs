@@ -0,0 +1,67 @@
+use std::fmt;
+
+use eyre::Result;
+use wasmparser::{Parser, Payload};
+
+/// Size breakdown of a compiled module.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleStats {
+    /// Every function defined by the module, in the order of the code section.
+    pub functions: Vec<FunctionStats>,
+    /// Bytes of all data segments together.
+    pub data_size: usize,
+    /// Bytes of the whole module.
+    pub total_size: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FunctionStats {
+    /// Declared locals, parameters excluded.
+    pub locals: u32,
+    /// Instructions of the body, including its final `end`.
+    pub instructions: usize,
+}
+
+/// Gathers the statistics of the module from its bytes.
+pub fn module_stats(wasm: &[u8]) -> Result<ModuleStats> {
+    let mut stats = ModuleStats {
+        total_size: wasm.len(),
+        ..ModuleStats::default()
+    };
+    for payload in Parser::new(0).parse_all(wasm) {
+        match payload? {
+            Payload::CodeSectionEntry(body) => {
+                let mut function = FunctionStats::default();
+                for local in body.get_locals_reader()? {
+                    let (count, _) = local?;
+                    function.locals += count;
+                }
+                let mut operators = body.get_operators_reader()?;
+                while !operators.eof() {
+                    operators.read()?;
+                    function.instructions += 1;
+                }
+                stats.functions.push(function);
+            }
+            Payload::DataSection(segments) => {
+                for segment in segments {
+                    stats.data_size += segment?.data.len();
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(stats)
+}
+
+impl fmt::Display for ModuleStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "functions: {}", self.functions.len())?;
+        for (index, function) in self.functions.iter().enumerate() {
+            writeln!(f, "  #{}: {} locals, {} instructions", index, function.locals, function.instructions)?;
+        }
+        writeln!(f, "locals: {}", self.functions.iter().map(|function| function.locals).sum::<u32>())?;
+        writeln!(f, "data: {} bytes", self.data_size)?;
+        write!(f, "total: {} bytes", self.total_size)
+    }
+}
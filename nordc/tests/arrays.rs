@@ -0,0 +1,102 @@
+use nordc::mir::{self, ValueType, WasmOptions};
+use nordc::runtime::Runtime;
+use nordc::{compile_source, mir_to_wasm_bytes, parse_to_ast, run_source_capturing};
+
+#[test]
+fn reads_an_element() {
+    let (result, _) = run_source_capturing("let a = [10, 20, 30]; a[1]", &[]).expect("Failed to run");
+    assert_eq!(result, 20);
+    let (result, _) = run_source_capturing("[10, 20, 30][2]", &[]).expect("Failed to run");
+    assert_eq!(result, 30);
+}
+
+#[test]
+fn writes_an_element() {
+    let (result, _) = run_source_capturing("let a = [1, 2]; a[0] = a[1] * 21; a[0]", &[]).expect("Failed to run");
+    assert_eq!(result, 42);
+}
+
+#[test]
+fn traps_out_of_bounds() {
+    assert!(run_source_capturing("let a = [1, 2]; a[2]", &[]).is_err());
+    assert!(run_source_capturing("let a = [1, 2]; a[-1]", &[]).is_err());
+}
+
+#[test]
+fn host_reads_a_returned_array() {
+    let ast = parse_to_ast("[1, 2, 3]").expect("Failed to parse");
+    let (bytecode, result_type) = mir::compile_typed(&ast).expect("Failed to compile");
+    assert_eq!(result_type, ValueType::Array);
+    let wasm = mir_to_wasm_bytes(&bytecode, WasmOptions::for_program(&ast)).expect("Failed to lower");
+    let mut runtime = Runtime::new(&wasm).expect("Failed to instantiate");
+    let address = runtime.run(&[]).expect("Failed to run");
+    assert_ne!(address, 0);
+    assert_eq!(runtime.read_array(address).expect("Failed to read"), vec![1, 2, 3]);
+}
+
+#[test]
+fn host_rejects_the_null_address() {
+    let wasm = compile_source("[]").expect("Failed to compile");
+    let mut runtime = Runtime::new(&wasm).expect("Failed to instantiate");
+    let address = runtime.run(&[]).expect("Failed to run");
+    assert_eq!(runtime.read_array(address).expect("Failed to read"), Vec::<i64>::new());
+    assert!(runtime.read_array(0).is_err());
+}
+
+#[test]
+fn arrays_compare_by_contents() {
+    let run = |source| run_source_capturing(source, &[]).expect("Failed to run").0;
+    assert_eq!(run("[1, 2] == [1, 2]"), 1);
+    assert_eq!(run("[1, 2] == [1, 3]"), 0);
+    assert_eq!(run("[1, 2] == [1, 2, 3]"), 0);
+    assert_eq!(run("[] == []"), 1);
+    assert_eq!(run("[1, 2] != [1, 3]"), 1);
+    assert_eq!(run("let a = [1, 2]; let b = [1, 2]; a == b"), 1);
+    assert_eq!(run("let a = [4, 5]; let b = [4, 0]; b[1] = 5; a == b"), 1);
+}
+
+#[test]
+fn array_comparison_is_a_bool() {
+    let ast = parse_to_ast("[1] == [1]").expect("Failed to parse");
+    let (_, result_type) = mir::compile_typed(&ast).expect("Failed to compile");
+    assert_eq!(result_type, ValueType::Bool);
+}
+
+#[test]
+fn slices_an_array() {
+    let wasm = compile_source("slice([1, 2, 3, 4])(1)(3)").expect("Failed to compile");
+    let mut runtime = Runtime::new(&wasm).expect("Failed to instantiate");
+    let address = runtime.run(&[]).expect("Failed to run");
+    assert_eq!(runtime.read_array(address).expect("Failed to read the array"), vec![2, 3]);
+}
+
+#[test]
+fn slice_indices_count_from_the_end_and_are_clamped() {
+    let run = |source| run_source_capturing(source, &[]).expect("Failed to run").0;
+    assert_eq!(run("let s = slice([1, 2, 3, 4])(-3)(-1); s[0] * 10 + s[1]"), 23);
+    assert_eq!(run("let s = slice([1, 2, 3])(-10)(10); s[0] * 100 + s[1] * 10 + s[2]"), 123);
+    assert_eq!(run("slice([1, 2, 3])(2)(2) == []"), 1);
+    assert_eq!(run("let a = [5, 6]; let s = slice(a)(0)(2); s[0] = 7; a[0]"), 5);
+}
+
+#[test]
+fn slices_with_a_range_index() {
+    let run = |source| run_source_capturing(source, &[]).expect("Failed to run").0;
+    assert_eq!(run("[1, 2, 3, 4][1..3] == [2, 3]"), 1);
+    assert_eq!(run("let a = [1, 2, 3, 4]; let n = 1; a[n + 1..-1] == [3]"), 1);
+    assert_eq!(run("let a = [5, 6]; a[0..0] == []"), 1);
+    let ast = parse_to_ast("a[1..2]").expect("Failed to parse");
+    assert_eq!(ast.pretty(), "Block\n  Slice\n    Identifier a\n    Num 1\n    Num 2\n");
+    let err = run_source_capturing("let x = 1; x[0..1]", &[]).expect_err("Slice of a number compiled");
+    assert!(err.to_string().contains("`slice` expects an array, got Int"), "{}", err);
+}
+
+#[test]
+fn slice_rejects_a_start_after_the_end() {
+    let err = run_source_capturing("slice([1, 2, 3])(2)(1)", &[]).expect_err("Reversed slice accepted");
+    assert!(format!("{:#}", err).contains("slice start is after its end"), "{:#}", err);
+    let err = run_source_capturing("slice([1, 2])(1)", &[]).expect_err("Partial slice compiled");
+    assert!(err.to_string().contains("slice(a)(start)(end)"), "{}", err);
+    let err = run_source_capturing("slice(1)(0)(1)", &[]).expect_err("Slice of a number compiled");
+    assert!(err.to_string().contains("`slice` expects an array, got Int"), "{}", err);
+}
@@ -0,0 +1,85 @@
+use nordc::{lex_to_tokens, parse_to_ast};
+use nordc::lexer::Token;
+use nordc::run_source_capturing;
+
+fn run(source: &str) -> i64 {
+    run_source_capturing(source, &[]).expect("Failed to run").0
+}
+
+#[test]
+fn compound_operators_lex_as_one_token() {
+    let tokens = lex_to_tokens("+= -= *= /= %= + =").expect("Failed to lex");
+    assert_eq!(tokens, vec![
+        Token::AddAssign, Token::SubAssign, Token::MulAssign, Token::DivAssign, Token::ModAssign,
+        Token::OperatorAdd, Token::Assign,
+    ]);
+}
+
+#[test]
+fn compound_assignment_to_a_local() {
+    assert_eq!(run("let x = 1; x += 5; x"), 6);
+    assert_eq!(run("let x = 10; x -= 2; x *= 3; x"), 24);
+    assert_eq!(run("let x = 17; x /= 2; x %= 5; x"), 3);
+    assert_eq!(run("let i = 0; let sum = 0; while i < 4 do i += 1; sum += i end; sum"), 10);
+}
+
+#[test]
+fn compound_assignment_to_an_element() {
+    assert_eq!(run("let a = [3, 4]; a[0] *= 2; a[0]"), 6);
+    assert_eq!(run("let a = [3, 4]; a[1] -= a[0] + 1; a[1]"), 0);
+}
+
+#[test]
+fn compound_assignment_evaluates_the_target_once() {
+    let (result, output) = run_source_capturing("let a = [1, 2]; a[print(1)] += 10; a[1]", &[]).expect("Failed to run");
+    assert_eq!(result, 12);
+    assert_eq!(output, "1\n");
+}
+
+#[test]
+fn compound_assignment_is_its_own_node() {
+    let ast = parse_to_ast("a[i] -= 2").expect("Failed to parse");
+    assert_eq!(ast.pretty(), "\
+Block
+  Assign -=
+    Index
+      Identifier a
+      Identifier i
+    Num 2
+");
+}
+
+#[test]
+fn spelled_out_update_evaluates_the_target_twice() {
+    let (result, output) = run_source_capturing("let a = [1, 2]; a[print(1)] = a[print(1)] + 10; a[1]", &[]).expect("Failed to run");
+    assert_eq!(result, 12);
+    assert_eq!(output, "1\n1\n");
+}
+
+#[test]
+fn prefix_increment_and_decrement_update_a_local() {
+    assert_eq!(run("let x = 0; ++x; ++x"), 2);
+    assert_eq!(run("let x = 5; --x; x"), 4);
+    assert_eq!(run("let x = 1; let y = ++x * 10; x + y"), 22);
+    assert_eq!(run("let i = 0; let n = 0; while ++i <= 3 do n += i end; n"), 6);
+}
+
+#[test]
+fn doubled_signs_after_an_operand_stay_binary() {
+    assert_eq!(run("5--3"), 8);
+    assert_eq!(run("let x = 4; x--1"), 5);
+    assert_eq!(run("let x = 4; let y = 2; (x)--y"), 6);
+    // After an operator `--` still decrements
+    assert_eq!(run("let x = 4; let y = 2; x - --y + y"), 4);
+    assert_eq!(lex_to_tokens("x--1").expect("Failed to lex"), vec![
+        Token::Identifier("x".to_string()), Token::OperatorSub, Token::OperatorSub, Token::Integer(1),
+    ]);
+}
+
+#[test]
+fn increment_needs_a_variable() {
+    let err = run_source_capturing("++1", &[]).expect_err("Incremented a literal");
+    assert!(err.to_string().contains("`++` can only be applied to a variable"), "{}", err);
+    let err = run_source_capturing("let a = [1]; --a[0]", &[]).expect_err("Decremented an element");
+    assert!(err.to_string().contains("`--` can only be applied to a variable"), "{}", err);
+}
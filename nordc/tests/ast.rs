@@ -0,0 +1,50 @@
+use nordc::ast::{ExprKind, Span};
+use nordc::parse_to_ast;
+
+#[test]
+fn pretty_tree_shows_precedence() {
+    let ast = parse_to_ast("let x = 1 + 2 * 3").expect("Failed to parse");
+    assert_eq!(ast.pretty(), "\
+Block
+  Let x
+    Binary +
+      Num 1
+      Binary *
+        Num 2
+        Num 3
+");
+}
+
+#[test]
+fn pretty_tree_covers_aggregates_and_calls() {
+    let ast = parse_to_ast("let f = fn(a) a[0]; f([1, -2]); (#{k: true}).k").expect("Failed to parse");
+    assert_eq!(ast.pretty(), "\
+Block
+  Let f
+    Lambda a
+      Index
+        Identifier a
+        Num 0
+  Call
+    Identifier f
+    Array
+      Num 1
+      Unary -
+        Num 2
+  Member k
+    Object
+      Field k
+        Bool true
+");
+}
+
+#[test]
+fn nodes_carry_their_source_span() {
+    let ast = parse_to_ast("let x = 1 + 2").expect("Failed to parse");
+    assert_eq!(ast.span, Span::new(0, 13));
+    let ExprKind::Block(statements) = &ast.kind else { panic!("Program is not a block") };
+    let ExprKind::Let(_, value) = &statements[0].kind else { panic!("Not a binding") };
+    assert_eq!(value.span, Span::new(8, 13));
+    let ExprKind::BinaryOp(lhs, _, rhs) = &value.kind else { panic!("Not a binary operation") };
+    assert_eq!((lhs.span, rhs.span), (Span::new(8, 9), Span::new(12, 13)));
+}
@@ -0,0 +1,33 @@
+use nordc::lex_to_tokens;
+use nordc::lexer::Token;
+use nordc::run_source_capturing;
+
+fn run(source: &str) -> i64 {
+    run_source_capturing(source, &[]).expect("Failed to run").0
+}
+
+#[test]
+fn bitwise_operators_lex_apart_from_logical_ones() {
+    let tokens = lex_to_tokens("& && | || ^ << <= < >> >= >").expect("Failed to lex");
+    assert_eq!(tokens, vec![
+        Token::OperatorBitAnd, Token::OperatorAnd, Token::OperatorBitOr, Token::OperatorOr, Token::OperatorBitXor,
+        Token::OperatorShl, Token::OperatorLessEqual, Token::OperatorLess,
+        Token::OperatorShr, Token::OperatorGreaterEqual, Token::OperatorGreater,
+    ]);
+}
+
+#[test]
+fn bitwise_operators_on_constants_and_locals() {
+    assert_eq!(run("6 & 3"), 2);
+    assert_eq!(run("1 << 4"), 16);
+    assert_eq!(run("-8 >> 1"), -4);
+    assert_eq!(run("let a = 6; let b = 3; (a & b) + (a | b) * 10 + (a ^ b) * 100"), 2 + 70 + 500);
+    assert_eq!(run("let x = -8; x >> 1"), -4);
+    assert_eq!(run("let n = 65; 1 << n"), 2);
+}
+
+#[test]
+fn bitwise_precedence_sits_between_comparisons_and_arithmetic() {
+    assert_eq!(run("8 | 2 ^ 7 & 3 << 1 + 1"), 8 | (2 ^ (7 & (3 << 2))));
+    assert_eq!(run("let x = 6; if x & 1 == 0 then 1 else 0 end"), 1);
+}
@@ -0,0 +1,30 @@
+use nordc::mir::{self, ValueType};
+use nordc::{parse_to_ast, run_source_capturing};
+
+fn run(source: &str) -> i64 {
+    run_source_capturing(source, &[]).expect("Failed to run").0
+}
+
+#[test]
+fn discarded_statements_leave_a_balanced_stack() {
+    // The module only validates when the values of `1` and `2` are dropped
+    let (result, _) = run_source_capturing("block 1; 2; 3 end", &[]).expect("Failed to run");
+    assert_eq!(result, 3);
+}
+
+#[test]
+fn block_ending_in_a_comparison_is_a_bool() {
+    assert_eq!(run("block 1 < 2 end"), 1);
+    assert_eq!(run("let x = 3; let big = block let y = x * 2; y > 10 end; big"), 0);
+    assert_eq!(run("if block 2 >= 2 end then 7 else 8 end"), 7);
+    let ast = parse_to_ast("block let x = 1; x == 1 end").expect("Failed to parse");
+    assert_eq!(mir::compile_typed(&ast).expect("Failed to compile").1, ValueType::Bool);
+}
+
+#[test]
+fn empty_blocks_have_no_result() {
+    assert_eq!(run("block end; 5"), 5);
+    assert_eq!(run("let x = 1; if x < 2 then block end end; block block end end; x"), 1);
+    let ast = parse_to_ast("block end").expect("Failed to parse");
+    assert_eq!(mir::compile_typed(&ast).expect("Failed to compile").1, ValueType::Unit);
+}
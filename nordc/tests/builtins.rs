@@ -0,0 +1,53 @@
+use nordc::{compile_source, run_source_capturing};
+
+fn run(source: &str) -> i64 {
+    run_source_capturing(source, &[]).expect("Failed to run").0
+}
+
+#[test]
+fn min_and_max_pick_the_smaller_and_larger() {
+    assert_eq!(run("min(3)(7)"), 3);
+    assert_eq!(run("max(3)(7)"), 7);
+    assert_eq!(run("let a = -2; let b = 5; min(a)(b) + max(a)(b)"), 3);
+}
+
+#[test]
+fn min_and_max_chain_over_more_arguments() {
+    assert_eq!(run("let a = 4; min(min(a)(9))(2)"), 2);
+    assert_eq!(run("let a = 4; max(max(a)(9))(2)"), 9);
+}
+
+#[test]
+fn abs_of_negative_and_positive_numbers() {
+    assert_eq!(run("abs(-4)"), 4);
+    assert_eq!(run("let x = 4; abs(x)"), 4);
+    assert_eq!(run("let x = 0 - 9; abs(x)"), 9);
+}
+
+#[test]
+fn numeric_builtins_reject_other_types() {
+    let err = compile_source("abs(true)").expect_err("Boolean accepted");
+    assert!(err.to_string().contains("`abs` expects a number, got Bool"), "{}", err);
+    let err = compile_source("max(1)(1 == 1)").expect_err("Boolean accepted");
+    assert!(err.to_string().contains("`max` expects a number"), "{}", err);
+    let err = compile_source("min(1)").expect_err("Single argument accepted");
+    assert!(err.to_string().contains("min(a)(b)"), "{}", err);
+}
+
+#[test]
+fn pow_raises_to_integer_powers() {
+    assert_eq!(run("pow(2)(10)"), 1024);
+    assert_eq!(run("let b = 3; let e = 4; pow(b)(e)"), 81);
+    assert_eq!(run("let b = 0; pow(b)(0)"), 1);
+    assert_eq!(run("let b = -2; pow(b)(63)"), i64::MIN);
+}
+
+#[test]
+fn pow_fails_on_overflow_and_negative_exponents() {
+    let err = run_source_capturing("pow(2)(100)", &[]).expect_err("Overflow wrapped");
+    assert!(format!("{:#}", err).contains("integer overflow"), "{:#}", err);
+    let err = run_source_capturing("let b = 2; pow(b)(63)", &[]).expect_err("Overflow wrapped");
+    assert!(format!("{:#}", err).contains("integer overflow"), "{:#}", err);
+    let err = run_source_capturing("pow(2)(-1)", &[]).expect_err("Negative exponent accepted");
+    assert!(format!("{:#}", err).contains("negative exponent"), "{:#}", err);
+}
@@ -0,0 +1,50 @@
+use nordc::cache::ModuleCache;
+use nordc::mir::{self, WasmOptions};
+use nordc::mir_to_wasm_bytes;
+use nordc::parse_to_ast;
+use nordc::runtime::HostState;
+
+/// Runs the source through the cache, compiling it only when the cache does not hold it yet.
+fn run_cached(cache: &mut ModuleCache, source: &str) -> i64 {
+    let (module, result_type) = cache
+        .get_or_compile(source, || {
+            let ast = parse_to_ast(source)?;
+            let (bytecode, result_type) = mir::compile_typed(&ast)?;
+            Ok((mir_to_wasm_bytes(&bytecode, WasmOptions::for_program(&ast))?, result_type))
+        })
+        .expect("Failed to compile");
+    let mut runtime = cache.instantiate(module, HostState::default()).expect("Failed to instantiate");
+    let result = runtime.run(&[]).expect("Failed to run");
+    assert_eq!(result_type, mir::ValueType::Int);
+    result
+}
+
+#[test]
+fn identical_sources_are_compiled_once() {
+    let mut cache = ModuleCache::new().expect("Failed to create cache");
+    assert_eq!(run_cached(&mut cache, "let x = 20; x * 2 + 2"), 42);
+    assert_eq!(cache.compiles(), 1);
+    assert_eq!(run_cached(&mut cache, "let x = 20; x * 2 + 2"), 42);
+    assert_eq!(cache.compiles(), 1);
+
+    assert_eq!(run_cached(&mut cache, "1 + 1"), 2);
+    assert_eq!(cache.compiles(), 2);
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn failed_compiles_are_not_cached() {
+    let mut cache = ModuleCache::new().expect("Failed to create cache");
+    assert!(cache.get_or_compile("1 +", || Err(eyre::eyre!("unexpected end of input"))).is_err());
+    assert_eq!(cache.compiles(), 0);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn cached_modules_run_in_fresh_instances() {
+    let mut cache = ModuleCache::new().expect("Failed to create cache");
+    let source = "let a = [1, 2, 3]; a[2]";
+    assert_eq!(run_cached(&mut cache, source), 3);
+    assert_eq!(run_cached(&mut cache, source), 3);
+    assert_eq!(cache.compiles(), 1);
+}
@@ -0,0 +1,219 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+#[test]
+fn std_mode_prints_one_bare_result_per_line() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["--std", "-v"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start nordc");
+    child.stdin.take().expect("No stdin").write_all(b"1 + 1\n\nlet x = 2; x * 3\n1 +\n1 == 1\n").expect("Failed to write stdin");
+    let output = child.wait_with_output().expect("Failed to wait for nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "2\n6\ntrue\n");
+    // The broken line is reported on stderr without stopping the stream
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unexpected end of input"));
+}
+
+#[test]
+fn run_mode_prints_only_the_result() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["-e", "40 + 2", "--run"])
+        .output()
+        .expect("Failed to run nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["-v", "--run", "-e", "let x = 3; x < 4"])
+        .output()
+        .expect("Failed to run nordc");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "true\n");
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn run_mode_needs_a_script() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc")).arg("--run").output().expect("Failed to run nordc");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--run requires a script"));
+}
+
+#[test]
+fn time_flag_reports_every_stage() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["--run", "--time", "-e", "let x = 20; x * 2 + 2"])
+        .output()
+        .expect("Failed to run nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for stage in ["lexing", "parsing", "compiling", "lowering", "instantiating", "running"] {
+        assert!(stderr.lines().any(|line| line.starts_with(&format!("{}: ", stage))), "No {} duration in {}", stage, stderr);
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc")).args(["--run", "-e", "1"]).output().expect("Failed to run nordc");
+    assert!(output.stderr.is_empty(), "{}", String::from_utf8_lossy(&output.stderr));
+}
+
+#[test]
+fn constant_programs_are_not_compiled() {
+    let stages = |source: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+            .args(["--run", "--time", "-e", source])
+            .output()
+            .expect("Failed to run nordc");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        (String::from_utf8_lossy(&output.stdout).into_owned(), stderr)
+    };
+    let (stdout, stderr) = stages("2 + 2");
+    assert_eq!(stdout, "4\n");
+    assert!(!stderr.contains("compiling: "), "{}", stderr);
+    // The stages it does go through are still timed
+    for stage in ["lexing", "parsing", "evaluating"] {
+        assert!(stderr.lines().any(|line| line.starts_with(&format!("{}: ", stage))), "No {} duration in {}", stage, stderr);
+    }
+
+    let (stdout, stderr) = stages("let x = 2; x + 2");
+    assert_eq!(stdout, "4\n");
+    assert!(stderr.contains("compiling: "), "{}", stderr);
+}
+
+#[test]
+fn repeated_fragments_run_as_one_program() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["--run", "-e", "let x = 1", "-e", "let y = x + 1;", "-e", "", "-e", "x + y"])
+        .output()
+        .expect("Failed to run nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "3\n");
+}
+
+#[test]
+fn fragment_errors_point_at_their_line_and_column() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["--run", "-e", "let x = 1", "-e", "x + y"])
+        .output()
+        .expect("Failed to run nordc");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("line 2, column 5: Unknown variable: y"), "{}", stderr);
+    assert!(!stderr.contains("at 14..15"), "{}", stderr);
+}
+
+/// Pipes the lines into the interactive mode, returning everything it prints.
+fn run_session(lines: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .arg("--result-as-string")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to start nordc");
+    child.stdin.take().expect("No stdin").write_all(lines.as_bytes()).expect("Failed to write stdin");
+    let output = child.wait_with_output().expect("Failed to wait for nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+#[test]
+fn session_keeps_bindings_and_reassignments() {
+    assert_eq!(run_session("let x = 1\nx = 5\nx += 1; x * 2\nx\n"), "1\n()\n12\n6\n");
+}
+
+#[test]
+fn session_keeps_arrays_and_booleans() {
+    assert_eq!(run_session("let a = [1, 2]\na[1] = 7\nlet b = a[1] == 7\na[1]\nb\n"), "[1, 2]\n()\ntrue\n7\ntrue\n");
+}
+
+#[test]
+fn session_keeps_a_retried_line_once_it_runs() {
+    let output = run_session("let z = 0\nlet y = 10 / z\nz = 2\nlet y = 10 / z\ny + 1\n");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "0");
+    assert!(lines[1].starts_with("Err("), "{}", output);
+    assert_eq!(lines[lines.len() - 3..], ["()", "5", "6"]);
+}
+
+#[test]
+fn session_does_not_print_replayed_lines_again() {
+    assert_eq!(run_session("let x = print(1)\nx + 1\nx + 2\n"), "1\n1\n2\n3\n");
+}
+
+#[test]
+fn session_locates_errors_in_the_line_itself() {
+    let output = run_session("let long_name = [1, 2, 3]\nlong_name + nope\n");
+    assert!(output.contains("line 1, column 13"), "{}", output);
+}
+
+#[test]
+fn emit_writes_the_module_instead_of_running_it() {
+    let dir = tempfile::tempdir().expect("Failed to create a directory");
+    let wasm_path = dir.path().join("out.wasm");
+    let wat_path = dir.path().join("out.wat");
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["-e", "print(40 + 2)", "--emit"])
+        .arg(&wasm_path)
+        .arg("--emit-wat")
+        .arg(&wat_path)
+        .output()
+        .expect("Failed to run nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+    let wasm = std::fs::read(&wasm_path).expect("Failed to read the module");
+    assert_eq!(&wasm[..4], b"\0asm");
+    let wat = std::fs::read_to_string(&wat_path).expect("Failed to read the text module");
+    assert!(wat.starts_with("(module"), "{}", wat);
+}
+
+#[test]
+fn emit_to_an_unwritable_path_fails() {
+    let dir = tempfile::tempdir().expect("Failed to create a directory");
+    let path = dir.path().join("missing").join("out.wasm");
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["-e", "1", "--emit"])
+        .arg(&path)
+        .output()
+        .expect("Failed to run nordc");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains(&format!("Failed to write Wasm module to {}", path.display())), "{}", stderr);
+}
+
+#[test]
+fn args_are_passed_to_a_lambda_program() {
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["--run", "-e", "fn(a) fn(b) a * b", "--arg", "6", "--arg", "7"])
+        .output()
+        .expect("Failed to run nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc"))
+        .args(["--run", "-e", "fn(a) fn(b) a * b", "--arg", "6"])
+        .output()
+        .expect("Failed to run nordc");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("`main` takes 2 argument(s) but 1 were given"), "{}", stderr);
+}
+
+#[test]
+fn empty_programs_evaluate_to_unit() {
+    let dir = tempfile::tempdir().expect("Failed to create a directory");
+    let path = dir.path().join("empty.nd");
+    std::fs::write(&path, "").expect("Failed to write the script");
+    let output = Command::new(env!("CARGO_BIN_EXE_nordc")).arg("-i").arg(&path).output().expect("Failed to run nordc");
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "()\n");
+
+    for source in ["", "  \n\t ", "// nothing but a comment"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_nordc")).args(["-e", source]).output().expect("Failed to run nordc");
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "()\n", "{:?}", source);
+    }
+}
@@ -0,0 +1,52 @@
+use nordc::ast::{ExprKind, Opcode};
+use nordc::{parse_to_ast, run_source_capturing};
+
+fn run(source: &str) -> i64 {
+    run_source_capturing(source, &[]).expect("Failed to run").0
+}
+
+/// The operator of a program made of a single binary operation.
+fn parsed_opcode(source: &str) -> Opcode {
+    let ast = parse_to_ast(source).expect("Failed to parse");
+    let ExprKind::Block(statements) = ast.kind else { panic!("Program is not a block") };
+    match &statements[0].kind {
+        ExprKind::BinaryOp(_, opcode, _) => *opcode,
+        kind => panic!("Not a binary operation: {:?}", kind),
+    }
+}
+
+#[test]
+fn ordering_operators_parse_to_their_opcodes() {
+    assert!(matches!(parsed_opcode("a < b"), Opcode::Less));
+    assert!(matches!(parsed_opcode("a <= b"), Opcode::LessEqual));
+    assert!(matches!(parsed_opcode("a > b"), Opcode::Greater));
+    assert!(matches!(parsed_opcode("a >= b"), Opcode::GreaterEqual));
+}
+
+#[test]
+fn ordering_comparisons_on_constants() {
+    assert_eq!([run("1 < 2"), run("2 < 2"), run("3 < 2")], [1, 0, 0]);
+    assert_eq!([run("1 <= 2"), run("2 <= 2"), run("3 <= 2")], [1, 1, 0]);
+    assert_eq!([run("1 > 2"), run("2 > 2"), run("3 > 2")], [0, 0, 1]);
+    assert_eq!([run("1 >= 2"), run("2 >= 2"), run("3 >= 2")], [0, 1, 1]);
+}
+
+#[test]
+fn ordering_comparisons_on_locals_are_signed() {
+    let compare = |op: &str| -> Vec<i64> {
+        [(-1, 2), (2, 2), (2, -1)].iter()
+            .map(|(a, b)| run(&format!("let a = {}; let b = {}; a {} b", a, b, op)))
+            .collect()
+    };
+    assert_eq!(compare("<"), [1, 0, 0]);
+    assert_eq!(compare("<="), [1, 1, 0]);
+    assert_eq!(compare(">"), [0, 0, 1]);
+    assert_eq!(compare(">="), [0, 1, 1]);
+}
+
+#[test]
+fn ordering_comparisons_drive_control_flow() {
+    assert_eq!(run("let i = 0; while i < 5 do i = i + 1 end; i"), 5);
+    assert_eq!(run("let i = 10; while i >= 3 do i = i - 3 end; i"), 1);
+    assert_eq!(run("let x = 7; (if x > 5 then 1 else 0 end) + (if x <= 7 then 10 else 0 end)"), 11);
+}
@@ -0,0 +1,18 @@
+use nordc::expected_tokens;
+
+#[test]
+fn expects_an_operand_after_a_binding() {
+    let expected = expected_tokens("let x =").expect("Failed to parse");
+    for terminal in ["\"int\"", "\"identifier\"", "\"bool\"", "\"[\"", "\"if\""] {
+        assert!(expected.iter().any(|expected| expected == terminal), "{} missing from {:?}", terminal, expected);
+    }
+    assert!(!expected.iter().any(|expected| expected == "\"else\""), "{:?}", expected);
+}
+
+#[test]
+fn expects_the_rest_of_an_if() {
+    let expected = expected_tokens("if x then 1").expect("Failed to parse");
+    assert!(expected.iter().any(|expected| expected == "\"else\""), "{:?}", expected);
+    assert!(expected.iter().any(|expected| expected == "\"end\""), "{:?}", expected);
+    assert!(expected_tokens("1 ` 2").is_err());
+}
@@ -0,0 +1,9 @@
+use nordc::compile_source;
+
+#[test]
+fn compiling_twice_yields_identical_bytes() {
+    let source = "let a = 1; let b = 2; let i = 0; while i < 3 do print(a + b * i); i = i + 1 end; a";
+    let first = compile_source(source).expect("Failed to compile");
+    let second = compile_source(source).expect("Failed to compile");
+    assert_eq!(first, second);
+}
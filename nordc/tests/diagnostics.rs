@@ -0,0 +1,64 @@
+use nordc::diagnostic::SourceError;
+use nordc::{parse_to_ast, LexicalError};
+
+#[test]
+fn unexpected_token_names_line_and_token() {
+    let err = parse_to_ast("let = 5").expect_err("Missing name accepted");
+    let message = err.to_string();
+    assert!(message.starts_with("line 1, column 5: unexpected `=`, expected `identifier`"), "{}", message);
+    assert!(message.ends_with("1 | let = 5\n  |     ^"), "{}", message);
+}
+
+#[test]
+fn error_on_a_later_line_underlines_the_token() {
+    let err = parse_to_ast("let x = 1;\nlet y = x +* 2").expect_err("Dangling operator accepted");
+    let source_error = err.downcast_ref::<SourceError>().expect("Not a source error");
+    assert_eq!((source_error.line, source_error.col), (2, 12));
+    assert_eq!(source_error.source_line, "let y = x +* 2");
+}
+
+#[test]
+fn unexpected_end_of_input_points_past_the_last_character() {
+    let err = parse_to_ast("1 +").expect_err("Incomplete input accepted");
+    let source_error = err.downcast_ref::<SourceError>().expect("Not a source error");
+    assert!(source_error.message.starts_with("unexpected end of input"), "{}", source_error.message);
+    assert_eq!((source_error.line, source_error.col, source_error.width), (1, 4, 1));
+}
+
+#[test]
+fn float_literal_is_a_syntax_error() {
+    let err = parse_to_ast("let x = 0x1.8p3").expect_err("Float literal parsed");
+    let source_error = err.downcast_ref::<SourceError>().expect("Not a source error");
+    assert_eq!((source_error.line, source_error.col, source_error.width), (1, 9, 7));
+    assert_eq!(source_error.message, "float literal `0x1.8p3` is not supported, programs only compute with integers");
+}
+
+#[test]
+fn invalid_character_is_a_lexer_error() {
+    let err = parse_to_ast("1 ` 2").expect_err("Backtick accepted");
+    assert!(err.downcast_ref::<LexicalError>().is_some(), "Not a lexer error: {}", err);
+    assert_eq!(err.to_string(), "unexpected character '`' at byte 2");
+}
+
+#[test]
+fn compile_error_points_at_the_expression() {
+    let err = nordc::compile_source("let x = 1;\nx + y").expect_err("Unknown variable compiled");
+    let source_error = err.downcast_ref::<SourceError>().expect("Not a source error");
+    assert_eq!((source_error.line, source_error.col, source_error.width), (2, 5, 1));
+    assert_eq!(source_error.message, "Unknown variable: y");
+}
+
+#[test]
+fn break_and_continue_outside_a_loop_are_compile_errors() {
+    for (source, message, col) in [
+        ("break 5", "`break` outside of a loop", 1),
+        ("continue", "`continue` outside of a loop", 1),
+        ("let x = 1; if x > 0 then continue end", "`continue` outside of a loop", 26),
+    ] {
+        let err = nordc::compile_source(source).expect_err("Compiled without a loop");
+        let source_error = err.downcast_ref::<SourceError>().expect("Not a source error");
+        assert_eq!((source_error.line, source_error.col), (1, col), "{}", source);
+        assert_eq!(source_error.message, message);
+    }
+    assert!(nordc::compile_source("let i = 0; while i < 3 do i = i + 1; continue end; loop break 5 end").is_ok());
+}
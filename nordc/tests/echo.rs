@@ -0,0 +1,14 @@
+use nordc::mir::WasmOptions;
+use nordc::runtime::Runtime;
+use nordc::{compile_to_mir, echo_statements, mir_to_wasm_bytes, parse_to_ast};
+
+#[test]
+fn echoes_every_statement() {
+    let ast = echo_statements(parse_to_ast("let x = 2; x * 10; x + 1").expect("Failed to parse"));
+    let bytecode = compile_to_mir(&ast).expect("Failed to compile");
+    let wasm = mir_to_wasm_bytes(&bytecode, WasmOptions::default()).expect("Failed to lower");
+    let mut runtime = Runtime::new_capturing(&wasm).expect("Failed to instantiate");
+    let result = runtime.run(&[]).expect("Failed to run");
+    assert_eq!(runtime.take_output(), "2\n20\n");
+    assert_eq!(result, 3);
+}
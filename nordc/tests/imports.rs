@@ -0,0 +1,47 @@
+use std::fs;
+
+use nordc::compile_file;
+use nordc::runtime::Runtime;
+
+fn run_file(path: &std::path::Path) -> eyre::Result<i64> {
+    let wasm = compile_file(path)?;
+    Runtime::new(&wasm)?.run(&[])
+}
+
+#[test]
+fn imports_the_bindings_of_another_file() {
+    let dir = tempfile::tempdir().expect("Failed to create a directory");
+    fs::write(dir.path().join("math.nord"), "let side = 7; let square = side * side; square + 1").expect("Failed to write");
+    fs::write(dir.path().join("main.nord"), "import \"math.nord\"; square - side").expect("Failed to write");
+    assert_eq!(run_file(&dir.path().join("main.nord")).expect("Failed to run"), 42);
+}
+
+#[test]
+fn imports_are_relative_to_the_importing_file() {
+    let dir = tempfile::tempdir().expect("Failed to create a directory");
+    fs::create_dir(dir.path().join("lib")).expect("Failed to create a directory");
+    fs::write(dir.path().join("lib/base.nord"), "let base = 40").expect("Failed to write");
+    fs::write(dir.path().join("lib/more.nord"), "import \"base.nord\"; let more = base + 2").expect("Failed to write");
+    fs::write(dir.path().join("main.nord"), "import \"lib/more.nord\"; more").expect("Failed to write");
+    assert_eq!(run_file(&dir.path().join("main.nord")).expect("Failed to run"), 42);
+}
+
+#[test]
+fn circular_imports_are_rejected() {
+    let dir = tempfile::tempdir().expect("Failed to create a directory");
+    fs::write(dir.path().join("a.nord"), "import \"b.nord\"; let a = 1").expect("Failed to write");
+    fs::write(dir.path().join("b.nord"), "import \"a.nord\"; let b = 2").expect("Failed to write");
+    let err = run_file(&dir.path().join("a.nord")).expect_err("Circular import resolved");
+    assert!(err.to_string().contains("Circular import"), "{}", err);
+}
+
+#[test]
+fn missing_and_nested_imports_are_errors() {
+    let dir = tempfile::tempdir().expect("Failed to create a directory");
+    fs::write(dir.path().join("main.nord"), "import \"missing.nord\"; 1").expect("Failed to write");
+    let err = run_file(&dir.path().join("main.nord")).expect_err("Missing import resolved");
+    assert!(err.to_string().contains("Failed to find imported file"), "{}", err);
+    fs::write(dir.path().join("main.nord"), "block import \"missing.nord\" end; 1").expect("Failed to write");
+    let err = run_file(&dir.path().join("main.nord")).expect_err("Nested import compiled");
+    assert!(err.to_string().contains("only allowed at the top level"), "{}", err);
+}
@@ -0,0 +1,21 @@
+use nordc::parse_to_ast;
+
+const KEYWORDS: &[&str] = &[
+    "let", "fn", "if", "then", "else", "return", "block", "end", "loop", "while", "do", "break", "continue", "import",
+];
+
+#[test]
+fn reserved_keywords_cannot_be_bound() {
+    for keyword in KEYWORDS {
+        let err = parse_to_ast(&format!("let {} = 1", keyword)).expect_err(keyword);
+        let expected = format!("line 1, column 5: `{}` is a reserved keyword and cannot be used as an identifier", keyword);
+        assert!(err.to_string().contains(&expected), "{}", err);
+        assert!(err.to_string().ends_with(&format!("    {}", "^".repeat(keyword.len()))), "{}", err);
+    }
+}
+
+#[test]
+fn reserved_keywords_cannot_be_parameters() {
+    let err = parse_to_ast("fn(loop) 1").expect_err("Keyword accepted as a parameter");
+    assert!(err.to_string().contains("`loop` is a reserved keyword"), "{}", err);
+}
@@ -0,0 +1,63 @@
+use std::time::{Duration, Instant};
+
+use nordc::mir::WasmOptions;
+use nordc::runtime::Runtime;
+use nordc::{compile_source, compile_to_mir, mir_to_wasm_bytes, parse_to_ast};
+
+fn run_with_max_steps(source: &str, max_steps: u64) -> eyre::Result<i64> {
+    let ast = parse_to_ast(source)?;
+    let bytecode = compile_to_mir(&ast)?;
+    let options = WasmOptions {
+        max_steps: Some(max_steps),
+        ..WasmOptions::default()
+    };
+    let wasm = mir_to_wasm_bytes(&bytecode, options)?;
+    Runtime::new(&wasm)?.run(&[])
+}
+
+#[test]
+fn bounded_loop_stays_within_the_step_limit() {
+    let result = run_with_max_steps("let i = 0; while i < 10 do i = i + 1 end; i", 100).expect("Failed to run");
+    assert_eq!(result, 10);
+}
+
+#[test]
+fn infinite_loop_exceeds_the_step_limit() {
+    let err = run_with_max_steps("loop end; 1", 100).expect_err("Infinite loop finished");
+    assert!(err.to_string().contains("step limit exceeded"), "{}", err);
+}
+
+#[test]
+fn bounded_loop_runs_within_the_fuel_budget() {
+    let wasm = compile_source("let i = 0; while i < 100 do i = i + 1 end; i").expect("Failed to compile");
+    let result = Runtime::new(&wasm).and_then(|mut runtime| runtime.run_with_fuel(&[], 1_000_000)).expect("Failed to run");
+    assert_eq!(result, 100);
+}
+
+#[test]
+fn infinite_loop_runs_out_of_fuel() {
+    let wasm = compile_source("loop end; 1").expect("Failed to compile");
+    let err = Runtime::new(&wasm).and_then(|mut runtime| runtime.run_with_fuel(&[], 10_000)).expect_err("Infinite loop finished");
+    assert!(err.to_string().contains("out of fuel"), "{}", err);
+}
+
+#[test]
+fn long_running_loop_times_out() {
+    let wasm = compile_source("loop end; 1").expect("Failed to compile");
+    let mut runtime = Runtime::new(&wasm).expect("Failed to instantiate");
+    let start = Instant::now();
+    let err = runtime.run_with_timeout(&[], Duration::from_millis(50)).expect_err("Infinite loop finished");
+    assert!(err.to_string().contains("timed out"), "{}", err);
+    assert!(start.elapsed() < Duration::from_secs(5), "Interrupted only after {:?}", start.elapsed());
+}
+
+#[test]
+fn timeout_returns_as_soon_as_the_program_finishes() {
+    let wasm = compile_source("1 + 2").expect("Failed to compile");
+    let mut runtime = Runtime::new(&wasm).expect("Failed to instantiate");
+    let start = Instant::now();
+    assert_eq!(runtime.run_with_timeout(&[], Duration::from_secs(60)).expect("Failed to run"), 3);
+    assert!(start.elapsed() < Duration::from_secs(5), "Waited {:?} for the timeout thread", start.elapsed());
+    // The deadline is lifted again afterwards
+    assert_eq!(runtime.run(&[]).expect("Failed to run"), 3);
+}
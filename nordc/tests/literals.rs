@@ -0,0 +1,33 @@
+use nordc::ast::ExprKind;
+use nordc::parse_to_ast;
+
+/// Parses a program made of a single expression and returns that expression.
+fn parse_single(source: &str) -> ExprKind {
+    match parse_to_ast(source).expect("Failed to parse").kind {
+        ExprKind::Block(mut exprs) if exprs.len() == 1 => exprs.remove(0).kind,
+        ast => panic!("Not a single expression: {:?}", ast),
+    }
+}
+
+#[test]
+fn array_literals_accept_a_trailing_comma() {
+    assert!(matches!(parse_single("[]"), ExprKind::Array(elems) if elems.is_empty()));
+    assert!(matches!(parse_single("[1,]"), ExprKind::Array(elems) if elems.len() == 1));
+    assert!(matches!(parse_single("[1, 2, 3,]"), ExprKind::Array(elems) if elems.len() == 3));
+    assert!(matches!(parse_single("[\n  1,\n  2,\n]"), ExprKind::Array(elems) if elems.len() == 2));
+}
+
+#[test]
+fn object_literals_accept_a_trailing_comma() {
+    assert!(matches!(parse_single("#{}"), ExprKind::Object(fields) if fields.is_empty()));
+    assert!(matches!(parse_single("#{a: 1,}"), ExprKind::Object(fields) if fields.len() == 1));
+    let ExprKind::Object(fields) = parse_single("#{a: 1, b: 2,}") else { panic!("Not an object") };
+    let keys: Vec<&str> = fields.iter().map(|(key, _)| key.as_str()).collect();
+    assert_eq!(keys, ["a", "b"]);
+}
+
+#[test]
+fn a_lone_comma_is_not_a_literal() {
+    assert!(parse_to_ast("[,]").is_err());
+    assert!(parse_to_ast("[1,,]").is_err());
+}
@@ -0,0 +1,35 @@
+use nordc::mir::{Mir, WasmOptions};
+use nordc::mir_to_wasm_bytes;
+use nordc::runtime::Runtime;
+
+fn run_mir(bytecode: &[Mir]) -> i64 {
+    let wasm = mir_to_wasm_bytes(bytecode, WasmOptions::default()).expect("Failed to lower");
+    Runtime::new(&wasm).and_then(|mut runtime| runtime.run(&[])).expect("Failed to run")
+}
+
+#[test]
+fn drop_discards_the_top_of_the_stack() {
+    assert_eq!(run_mir(&[Mir::ConstI64(1), Mir::ConstI64(2), Mir::Drop]), 1);
+}
+
+#[test]
+fn if_without_else_runs_its_arm_only_when_true() {
+    let assign_if = |cond| vec![
+        Mir::ConstI64(1),
+        Mir::LocalSet(0),
+        Mir::ConstI64(cond),
+        Mir::IfElse(vec![Mir::ConstI64(5), Mir::LocalSet(0)], None),
+        Mir::LocalGet(0),
+    ];
+    assert_eq!(run_mir(&assign_if(1)), 5);
+    assert_eq!(run_mir(&assign_if(0)), 1);
+}
+
+#[test]
+fn nested_sequences_lower_inside_each_other() {
+    let bytecode = [Mir::Block(vec![
+        Mir::ConstI64(0),
+        Mir::IfElse(vec![Mir::ConstI64(1)], Some(vec![Mir::Block(vec![Mir::ConstI64(2), Mir::ConstI64(3), Mir::Drop])])),
+    ])];
+    assert_eq!(run_mir(&bytecode), 2);
+}
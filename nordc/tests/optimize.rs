@@ -0,0 +1,11 @@
+use nordc::compile_source;
+use nordc::optimize::optimize;
+
+#[test]
+fn level_four_shrinks_a_constant_heavy_module() {
+    let wasm = compile_source("let a = 1; let b = a + 2; let c = b * 3; let d = c - a; let e = [a, b, c, d]; e[3] + d").expect("Failed to compile");
+    let unoptimized = optimize(&wasm, 0).expect("Failed to optimize");
+    let optimized = optimize(&wasm, 4).expect("Failed to optimize");
+    assert_eq!(unoptimized, wasm);
+    assert!(optimized.len() < unoptimized.len(), "{} >= {}", optimized.len(), unoptimized.len());
+}
@@ -0,0 +1,71 @@
+use nordc::mir::{Overflow, WasmOptions};
+use nordc::runtime::Runtime;
+use nordc::{compile_to_mir, mir_to_wasm_bytes, parse_to_ast};
+
+fn run_with_overflow(source: &str, args: &[i64], overflow: Overflow) -> eyre::Result<i64> {
+    let ast = parse_to_ast(source)?;
+    let bytecode = compile_to_mir(&ast)?;
+    let options = WasmOptions {
+        overflow,
+        ..WasmOptions::for_program(&ast)
+    };
+    let wasm = mir_to_wasm_bytes(&bytecode, options)?;
+    Runtime::new(&wasm)?.run(args)
+}
+
+const MUL: &str = "fn(a) fn(b) a * b";
+const ADD: &str = "fn(a) fn(b) a + b";
+const SUB: &str = "fn(a) fn(b) a - b";
+
+#[test]
+fn overflow_modes_parse_from_their_names() {
+    assert_eq!("wrap".parse(), Ok(Overflow::Wrap));
+    assert_eq!("checked".parse(), Ok(Overflow::Checked));
+    assert_eq!("saturate".parse(), Ok(Overflow::Saturate));
+    assert!("clamp".parse::<Overflow>().is_err());
+}
+
+#[test]
+fn wrap_mode_wraps_around() {
+    assert_eq!(run_with_overflow(MUL, &[i64::MAX, 2], Overflow::Wrap).expect("Failed to run"), -2);
+    assert_eq!(run_with_overflow(ADD, &[i64::MAX, 1], Overflow::Wrap).expect("Failed to run"), i64::MIN);
+    assert_eq!(run_with_overflow(SUB, &[i64::MIN, 1], Overflow::Wrap).expect("Failed to run"), i64::MAX);
+}
+
+#[test]
+fn checked_mode_aborts_on_overflow() {
+    for (source, args) in [(MUL, [i64::MAX, 2]), (MUL, [i64::MIN, -1]), (ADD, [i64::MAX, 1]), (SUB, [i64::MIN, 1])] {
+        let err = run_with_overflow(source, &args, Overflow::Checked).expect_err("Overflow wrapped");
+        assert!(format!("{:#}", err).contains("integer overflow"), "{:#}", err);
+    }
+}
+
+#[test]
+fn checked_mode_keeps_results_in_range() {
+    assert_eq!(run_with_overflow(MUL, &[i64::MAX / 2, 2], Overflow::Checked).expect("Failed to run"), i64::MAX - 1);
+    assert_eq!(run_with_overflow(MUL, &[i64::MIN, 1], Overflow::Checked).expect("Failed to run"), i64::MIN);
+    assert_eq!(run_with_overflow(MUL, &[i64::MAX, 0], Overflow::Checked).expect("Failed to run"), 0);
+    assert_eq!(run_with_overflow(MUL, &[-1, i64::MAX], Overflow::Checked).expect("Failed to run"), -i64::MAX);
+    assert_eq!(run_with_overflow(ADD, &[i64::MAX, -1], Overflow::Checked).expect("Failed to run"), i64::MAX - 1);
+}
+
+#[test]
+fn saturate_mode_clamps_to_the_bounds() {
+    let run = |source, args: [i64; 2]| run_with_overflow(source, &args, Overflow::Saturate).expect("Failed to run");
+    assert_eq!(run(MUL, [i64::MAX, 2]), i64::MAX);
+    assert_eq!(run(MUL, [i64::MAX, -2]), i64::MIN);
+    assert_eq!(run(MUL, [i64::MIN, -1]), i64::MAX);
+    assert_eq!(run(MUL, [i64::MAX / 2, 2]), i64::MAX - 1);
+    assert_eq!(run(ADD, [i64::MAX, 1]), i64::MAX);
+    assert_eq!(run(ADD, [i64::MIN, -1]), i64::MIN);
+    assert_eq!(run(SUB, [i64::MIN, 1]), i64::MIN);
+    assert_eq!(run(SUB, [i64::MAX, -1]), i64::MAX);
+}
+
+#[test]
+fn constant_overflow_is_left_to_the_mode() {
+    let source = "9223372036854775807 * 2";
+    assert_eq!(run_with_overflow(source, &[], Overflow::Wrap).expect("Failed to run"), -2);
+    assert!(run_with_overflow(source, &[], Overflow::Checked).is_err());
+    assert_eq!(run_with_overflow(source, &[], Overflow::Saturate).expect("Failed to run"), i64::MAX);
+}
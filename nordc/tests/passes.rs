@@ -0,0 +1,48 @@
+use nordc::mir::{self, Mir, WasmOptions};
+use nordc::{compile_to_mir, parse_to_ast};
+
+#[test]
+fn blanked_out_instructions_lower_like_removed_ones() {
+    let ast = parse_to_ast("let x = 2; if x > 1 then x * 3 else 0 end").expect("Failed to parse");
+    let bytecode = compile_to_mir(&ast).expect("Failed to compile");
+
+    // A pass blanking out instructions in place, at the top level and inside the `if`
+    let mut blanked: Vec<Mir> = bytecode.iter().cloned().flat_map(|op| [op, Mir::Nop]).collect();
+    for op in &mut blanked {
+        if let Mir::IfElse(then_ops, _) = op {
+            then_ops.insert(0, Mir::Nop);
+        }
+    }
+    mir::remove_nops(&mut blanked);
+
+    let options = WasmOptions::default();
+    assert_eq!(
+        mir::to_wasm_module(&blanked, options).expect("Failed to lower"),
+        mir::to_wasm_module(&bytecode, options).expect("Failed to lower"),
+    );
+}
+
+#[test]
+fn folds_constant_operands() {
+    let compile = |source| compile_to_mir(&parse_to_ast(source).expect("Failed to parse")).expect("Failed to compile");
+    assert_eq!(compile("2 + 3"), vec![Mir::ConstI64(5)]);
+    assert_eq!(compile("2 + 3 * 4 - -1"), vec![Mir::ConstI64(15)]);
+    assert_eq!(compile("1 < 2"), vec![Mir::ConstI64(1)]);
+    // Division by zero still traps at runtime
+    assert_eq!(compile("1 / 0"), vec![Mir::Block(vec![Mir::ConstI64(1), Mir::ConstI64(0), Mir::DivI64])]);
+}
+
+#[test]
+fn evaluates_constant_programs_without_compiling() {
+    let eval = |source| mir::const_eval(&parse_to_ast(source).expect("Failed to parse"));
+    assert_eq!(eval("2 + 2"), Some(4));
+    assert_eq!(eval("(1 + 2) * -3"), Some(-9));
+    assert_eq!(eval("(6 & 3) | (1 << 4)"), Some(18));
+    assert_eq!(eval("x + 2"), None);
+    assert_eq!(eval("let x = 2; x + 2"), None);
+    // Booleans print differently, traps and overflows are left to the compiled program
+    assert_eq!(eval("2 < 3"), None);
+    assert_eq!(eval("1 / 0"), None);
+    assert_eq!(eval("9223372036854775807 + 1"), None);
+    assert_eq!(eval("2 + 2;"), None);
+}
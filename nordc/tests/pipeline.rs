@@ -0,0 +1,16 @@
+use nordc::lexer::Token;
+use nordc::mir::WasmOptions;
+use nordc::{compile_source, compile_to_mir, lex_to_tokens, mir_to_wasm_bytes, parse_to_ast};
+
+#[test]
+fn stages_compose_into_compile_source() {
+    let source = "let x = 2; let a = [x, 3]; if a[0] < a[1] then x * 21 else 0 end";
+    let tokens = lex_to_tokens(source).expect("Failed to lex");
+    assert_eq!(tokens.first(), Some(&Token::KeywordLet));
+    assert_eq!(tokens.last(), Some(&Token::KeywordEnd));
+
+    let ast = parse_to_ast(source).expect("Failed to parse");
+    let bytecode = compile_to_mir(&ast).expect("Failed to compile");
+    let wasm = mir_to_wasm_bytes(&bytecode, WasmOptions::for_program(&ast)).expect("Failed to lower");
+    assert_eq!(wasm, compile_source(source).expect("Failed to compile the source"));
+}
@@ -0,0 +1,56 @@
+use nordc::ast::ExprKind;
+use nordc::mir::{self, ValueType};
+use nordc::{compile_to_mir, parse_to_ast, run_source_capturing};
+
+#[test]
+fn return_parses_with_and_without_a_value() {
+    let ast = parse_to_ast("return 1; return").expect("Failed to parse");
+    let ExprKind::Block(statements) = ast.kind else { panic!("Program is not a block") };
+    assert!(matches!(statements[0].kind, ExprKind::Return(Some(_))));
+    assert!(matches!(statements[1].kind, ExprKind::Return(None)));
+}
+
+#[test]
+fn return_skips_the_rest_of_the_program() {
+    let (result, output) = run_source_capturing("print(1); return 5; print(2); 7", &[]).expect("Failed to run");
+    assert_eq!((result, output.as_str()), (5, "1\n"));
+}
+
+#[test]
+fn return_leaves_nested_blocks_and_loops() {
+    let source = "let i = 0; loop i = i + 1; if i == 3 then return i * 10 end; if i == 5 then break end end; 0";
+    assert_eq!(run_source_capturing(source, &[]).expect("Failed to run").0, 30);
+}
+
+#[test]
+fn bare_return_in_a_unit_program() {
+    let ast = parse_to_ast("print(1); if true then return end; print(2); let x = 1").expect("Failed to parse");
+    let (_, result_type) = mir::compile_typed(&ast).expect("Failed to compile");
+    assert_eq!(result_type, ValueType::Unit);
+    let (_, output) = run_source_capturing("print(1); if true then return end; print(2); let x = 1", &[]).expect("Failed to run");
+    assert_eq!(output, "1\n");
+}
+
+#[test]
+fn return_must_agree_with_the_program_result() {
+    let ast = parse_to_ast("return 1; let x = 2").expect("Failed to parse");
+    let err = compile_to_mir(&ast).expect_err("Valued return in a unit program accepted");
+    assert!(err.to_string().contains("`return`"), "{}", err);
+}
+
+#[test]
+fn early_return_from_a_function_body() {
+    let source = "fn(n) block if n < 0 then return 0 end; n * 2 end";
+    assert_eq!(run_source_capturing(source, &[-3]).expect("Failed to run").0, 0);
+    assert_eq!(run_source_capturing(source, &[4]).expect("Failed to run").0, 8);
+}
+
+#[test]
+fn returning_arm_fits_a_valued_if() {
+    let source = "fn(n) block let half = if n % 2 == 1 then return else n / 2 end; print(half); let x = 0 end";
+    assert_eq!(run_source_capturing(source, &[3]).expect("Failed to run").1, "");
+    assert_eq!(run_source_capturing(source, &[8]).expect("Failed to run").1, "4\n");
+
+    let ast = parse_to_ast("let c = true; if c then return false else 1 == 1 end").expect("Failed to parse");
+    assert_eq!(mir::compile_typed(&ast).expect("Failed to compile").1, ValueType::Bool);
+}
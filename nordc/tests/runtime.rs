@@ -0,0 +1,69 @@
+use nordc::runtime::Runtime;
+
+const MODULE: &str = r#"
+    (module
+        (func (export "double") (param i64) (result i64)
+            local.get 0
+            i64.const 2
+            i64.mul)
+        (func (export "add") (param i64 i64) (result i64)
+            local.get 0
+            local.get 1
+            i64.add)
+        (func (export "flag") (param i32) (result i32)
+            local.get 0))
+"#;
+
+fn runtime() -> Runtime {
+    let wasm = wat::parse_str(MODULE).expect("Invalid WAT");
+    Runtime::new(&wasm).expect("Failed to instantiate")
+}
+
+#[test]
+fn exported_functions_are_called_by_name() {
+    let mut runtime = runtime();
+    assert_eq!(runtime.run_named::<i64>("double", &[21]).expect("Failed to call `double`"), 42);
+    assert_eq!(runtime.run_named::<i64>("add", &[40, 2]).expect("Failed to call `add`"), 42);
+}
+
+#[test]
+fn mismatched_calls_are_reported() {
+    let mut runtime = runtime();
+    let err = runtime.run_named::<i64>("triple", &[1]).expect_err("Missing export called");
+    assert!(err.to_string().contains("No exported function `triple`"), "{}", err);
+    let err = runtime.run_named::<i64>("add", &[1]).expect_err("Wrong arity accepted");
+    assert!(err.to_string().contains("`add` takes 2 argument(s) but 1 were given"), "{}", err);
+    let err = runtime.run_named::<i32>("flag", &[1]).expect_err("i32 parameter accepted");
+    assert!(err.to_string().contains("only i64 arguments"), "{}", err);
+    let err = runtime.run_named::<()>("double", &[1]).expect_err("Result type not checked");
+    assert!(err.to_string().contains("does not match"), "{}", err);
+}
+
+#[test]
+fn reset_starts_from_clean_globals() {
+    let wasm = wat::parse_str(r#"
+        (module
+            (global $count (mut i64) (i64.const 0))
+            (func (export "main") (result i64)
+                global.get $count
+                i64.const 1
+                i64.add
+                global.set $count
+                global.get $count))
+    "#).expect("Invalid WAT");
+    let mut runtime = Runtime::new(&wasm).expect("Failed to instantiate");
+    assert_eq!(runtime.run(&[]).expect("Failed to run"), 1);
+    assert_eq!(runtime.run(&[]).expect("Failed to run"), 2);
+    runtime.reset().expect("Failed to reset");
+    assert_eq!(runtime.run(&[]).expect("Failed to run"), 1);
+}
+
+#[test]
+fn reset_starts_from_a_clean_heap() {
+    let wasm = nordc::compile_source("[1, 2]").expect("Failed to compile");
+    let mut runtime = Runtime::new_capturing(&wasm).expect("Failed to instantiate");
+    let first = runtime.run(&[]).expect("Failed to run");
+    assert_ne!(runtime.run(&[]).expect("Failed to run"), first);
+    runtime.reset().expect("Failed to reset");
+    assert_eq!(runtime.run(&[]).expect("Failed to run"), first);
+}
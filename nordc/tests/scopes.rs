@@ -0,0 +1,24 @@
+use nordc::run_source_capturing;
+
+#[test]
+fn inner_binding_shadows_and_outer_is_restored() {
+    let (result, _) = run_source_capturing("let x = 1; let y = block let x = 2; x * 10 end; x + y", &[]).expect("Failed to run");
+    assert_eq!(result, 21);
+}
+
+#[test]
+fn inner_binding_does_not_leak() {
+    let (result, _) = run_source_capturing("let x = 1; if x == 1 then let x = 5; x else 0 end; x", &[]).expect("Failed to run");
+    assert_eq!(result, 1);
+}
+
+#[test]
+fn reading_an_unknown_variable_fails_to_compile() {
+    let err = run_source_capturing("x + 1", &[]).expect_err("Unknown variable compiled");
+    assert!(err.to_string().contains("Unknown variable: x"), "{}", err);
+}
+
+#[test]
+fn binding_is_not_visible_in_its_own_initializer() {
+    assert!(run_source_capturing("let x = x + 1", &[]).is_err());
+}
@@ -0,0 +1,14 @@
+use nordc::lexer::Token;
+use nordc::{lex_to_tokens, run_source_capturing};
+
+#[test]
+fn shebang_on_the_first_line_is_skipped() {
+    let (result, _) = run_source_capturing("#!/usr/bin/env nordc\nlet x = 40;\nx + 2", &[]).expect("Failed to run");
+    assert_eq!(result, 42);
+}
+
+#[test]
+fn hash_elsewhere_is_a_token() {
+    let tokens = lex_to_tokens("1\n#!").expect("Failed to lex");
+    assert_eq!(tokens, vec![Token::Integer(1), Token::Hash, Token::OperatorNot]);
+}
@@ -0,0 +1,10 @@
+(module
+  (type (;0;) (func (result i64)))
+  (memory (;0;) 1)
+  (global (;0;) (mut i32) i32.const 8)
+  (export "main" (func 0))
+  (export "memory" (memory 0))
+  (func (;0;) (type 0) (result i64)
+    i64.const 9
+  )
+)
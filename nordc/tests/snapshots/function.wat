@@ -0,0 +1,14 @@
+(module
+  (type (;0;) (func (param i64 i64) (result i64)))
+  (memory (;0;) 1)
+  (global (;0;) (mut i32) i32.const 8)
+  (export "main" (func 0))
+  (export "memory" (memory 0))
+  (func (;0;) (type 0) (param i64 i64) (result i64)
+    local.get 0
+    local.get 1
+    i64.mul
+    i64.const 1
+    i64.add
+  )
+)
@@ -0,0 +1,28 @@
+(module
+  (type (;0;) (func (result i64)))
+  (memory (;0;) 1)
+  (global (;0;) (mut i32) i32.const 8)
+  (export "main" (func 0))
+  (export "memory" (memory 0))
+  (func (;0;) (type 0) (result i64)
+    (local i64)
+    block (result i64) ;; label = @1
+      i64.const 3
+      local.set 0
+      local.get 0
+      i64.const 5
+      i64.lt_s
+      if (result i64) ;; label = @2
+        block (result i64) ;; label = @3
+          local.get 0
+          i64.const 2
+          i64.mul
+        end
+      else
+        block (result i64) ;; label = @3
+          local.get 0
+        end
+      end
+    end
+  )
+)
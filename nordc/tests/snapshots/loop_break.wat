@@ -0,0 +1,36 @@
+(module
+  (type (;0;) (func (result i64)))
+  (memory (;0;) 1)
+  (global (;0;) (mut i32) i32.const 8)
+  (export "main" (func 0))
+  (export "memory" (memory 0))
+  (func (;0;) (type 0) (result i64)
+    (local i64)
+    block (result i64) ;; label = @1
+      i64.const 0
+      local.set 0
+      block (result i64) ;; label = @2
+        loop ;; label = @3
+          local.get 0
+          i64.const 1
+          i64.add
+          local.set 0
+          local.get 0
+          i64.const 10
+          i64.eq
+          if (result i64) ;; label = @4
+            block (result i64) ;; label = @5
+              local.get 0
+              br 3 (;@2;)
+            end
+          else
+            i64.const 0
+          end
+          drop
+          br 0 (;@3;)
+        end
+        unreachable
+      end
+    end
+  )
+)
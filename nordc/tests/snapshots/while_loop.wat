@@ -0,0 +1,46 @@
+(module
+  (type (;0;) (func (result i64)))
+  (memory (;0;) 1)
+  (global (;0;) (mut i32) i32.const 8)
+  (export "main" (func 0))
+  (export "memory" (memory 0))
+  (func (;0;) (type 0) (result i64)
+    (local i64 i64 i64)
+    block (result i64) ;; label = @1
+      i64.const 0
+      local.set 0
+      i64.const 0
+      local.set 1
+      i64.const 0
+      local.set 2
+      block (result i64) ;; label = @2
+        loop ;; label = @3
+          local.get 0
+          i64.const 5
+          i64.lt_s
+          if (result i64) ;; label = @4
+            i64.const 0
+          else
+            local.get 2
+            br 2 (;@2;)
+          end
+          drop
+          local.get 1
+          local.get 0
+          i64.add
+          local.set 1
+          local.get 0
+          i64.const 1
+          i64.add
+          local.set 0
+          i64.const 0
+          local.set 2
+          br 0 (;@3;)
+        end
+        unreachable
+      end
+      drop
+      local.get 1
+    end
+  )
+)
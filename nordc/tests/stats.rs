@@ -0,0 +1,35 @@
+use nordc::compile_source;
+use nordc::stats::{module_stats, FunctionStats};
+
+#[test]
+fn counts_functions_and_instructions() {
+    let wasm = compile_source("let x = 1; x + 2").expect("Failed to compile");
+    let stats = module_stats(&wasm).expect("Failed to read module");
+    // block, i64.const, local.set, local.get, i64.const, i64.add, end of the block and end of `main`
+    assert_eq!(stats.functions, vec![FunctionStats { locals: 1, instructions: 8 }]);
+    assert_eq!(stats.data_size, 0);
+    assert_eq!(stats.total_size, wasm.len());
+}
+
+/// Instructions of `main` lowered from the MIR.
+fn main_instructions(bytecode: &[nordc::mir::Mir]) -> usize {
+    let wasm = nordc::mir_to_wasm_bytes(bytecode, nordc::mir::WasmOptions::default()).expect("Failed to lower");
+    module_stats(&wasm).expect("Failed to read module").functions[0].instructions
+}
+
+#[test]
+fn comparison_feeds_an_if_without_conversions() {
+    use nordc::mir::Mir;
+    let branch = || Mir::IfElse(vec![Mir::ConstI64(1)], Some(vec![Mir::ConstI64(2)]));
+    let direct = main_instructions(&[Mir::ConstI64(1), Mir::ConstI64(2), Mir::LessThanI64, branch()]);
+    // Going through a local keeps the comparison's i64, costing the local's set and get plus the extend and wrap
+    let stored = main_instructions(&[Mir::ConstI64(1), Mir::ConstI64(2), Mir::LessThanI64, Mir::LocalSet(0), Mir::LocalGet(0), branch()]);
+    assert_eq!(stored - direct, 4);
+
+    for source in ["let a = 1; let b = 2; if a < b then a else b end", "let i = 0; while i < 3 do i = i + 1 end"] {
+        let ast = nordc::parse_to_ast(source).expect("Failed to parse");
+        let bytecode = nordc::compile_to_mir(&ast).expect("Failed to compile");
+        let wat = nordc::mir::to_wat_text(&bytecode, nordc::mir::WasmOptions::default()).expect("Failed to print");
+        assert!(!wat.contains("i64.extend_i32_u") && !wat.contains("i32.wrap_i64"), "{}", wat);
+    }
+}
@@ -0,0 +1,33 @@
+use nordc::mir::{Mir, WasmOptions};
+use nordc::mir_to_wasm_bytes;
+use nordc::runtime::Runtime;
+
+/// Runs MIR that assigns local 0 only when `assign` is set, then reads it.
+///
+/// Every `let` assigns its local where it is declared, so no source program reads one before it was assigned.
+fn run_conditionally_assigned(assign: bool, strict_locals: bool) -> eyre::Result<i64> {
+    let bytecode = vec![
+        Mir::ConstI64(assign as i64),
+        Mir::IfElse(vec![Mir::ConstI64(5), Mir::LocalSet(0), Mir::ConstI64(0)], Some(vec![Mir::ConstI64(0)])),
+        Mir::Drop,
+        Mir::LocalGet(0),
+    ];
+    let options = WasmOptions {
+        strict_locals,
+        ..WasmOptions::default()
+    };
+    let wasm = mir_to_wasm_bytes(&bytecode, options)?;
+    Runtime::new(&wasm)?.run(&[])
+}
+
+#[test]
+fn unassigned_local_reads_zero_by_default() {
+    assert_eq!(run_conditionally_assigned(false, false).expect("Failed to run"), 0);
+    assert_eq!(run_conditionally_assigned(true, false).expect("Failed to run"), 5);
+}
+
+#[test]
+fn unassigned_local_traps_with_strict_locals() {
+    assert!(run_conditionally_assigned(false, true).is_err());
+    assert_eq!(run_conditionally_assigned(true, true).expect("Failed to run"), 5);
+}
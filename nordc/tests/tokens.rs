@@ -0,0 +1,85 @@
+use nordc::{lex_to_spanned_tokens, lex_to_tokens};
+use nordc::lexer::Token;
+use nordc::LexicalError;
+
+#[test]
+fn tokens_carry_their_byte_offsets() {
+    let tokens = lex_to_spanned_tokens("let x = 1").expect("Failed to lex");
+    assert_eq!(tokens, vec![
+        (0, Token::KeywordLet, 3),
+        (4, Token::Identifier("x".to_string()), 5),
+        (6, Token::Assign, 7),
+        (8, Token::Integer(1), 9),
+    ]);
+}
+
+#[test]
+fn comments_leave_no_tokens_behind() {
+    let tokens = lex_to_spanned_tokens("1 /* two */ + // three\n4").expect("Failed to lex");
+    let spans: Vec<(usize, usize)> = tokens.iter().map(|&(start, _, end)| (start, end)).collect();
+    assert_eq!(spans, vec![(0, 1), (12, 13), (23, 24)]);
+}
+
+#[test]
+fn hex_floats_lex_to_their_value() {
+    let tokens = lex_to_spanned_tokens("0x1.8p3 0xAp-1 0x.8p1").expect("Failed to lex");
+    assert_eq!(tokens, vec![
+        (0, Token::Float(12.0), 7),
+        (8, Token::Float(5.0), 14),
+        (15, Token::Float(1.0), 21),
+    ]);
+}
+
+#[test]
+fn hex_float_without_an_exponent_is_an_error() {
+    let err = lex_to_spanned_tokens("1 + 0x1.8").expect_err("Hex float without exponent lexed");
+    let lexical_error = err.downcast_ref::<LexicalError>().expect("Not a lexer error");
+    assert!(matches!(lexical_error, LexicalError::InvalidHexFloat((4, slice, 9)) if slice == "0x1.8"), "{:?}", lexical_error);
+}
+
+#[test]
+fn integer_literal_past_i64_max_is_an_error() {
+    let tokens = lex_to_spanned_tokens("9223372036854775807").expect("Failed to lex");
+    assert_eq!(tokens, vec![(0, Token::Integer(i64::MAX), 19)]);
+
+    let err = lex_to_spanned_tokens("let x = 9223372036854775808").expect_err("Overflowing literal lexed");
+    assert_eq!(err.to_string(), "integer literal too large for i64: `9223372036854775808` at 8..27");
+    let lexical_error = err.downcast_ref::<LexicalError>().expect("Not a lexer error");
+    assert!(matches!(lexical_error, LexicalError::IntegerOverflow((8, _, 27))), "{:?}", lexical_error);
+}
+
+#[test]
+fn prefixed_integers_lex_in_their_base() {
+    let tokens = lex_to_tokens("0xff 0XFF 0o17 0b1010 1_000_000 0xFF_FF 0b1111_0000").expect("Failed to lex");
+    let values: Vec<i64> = tokens.into_iter().map(|token| match token {
+        Token::Integer(value) => value,
+        token => panic!("Not an integer: {:?}", token),
+    }).collect();
+    assert_eq!(values, [255, 255, 15, 10, 1_000_000, 0xFFFF, 0b1111_0000]);
+}
+
+#[test]
+fn prefixed_integer_overflow_is_an_error_in_every_base() {
+    let binary = format!("0b1{}", "0".repeat(63));
+    for source in ["0xFFFF_FFFF_FFFF_FFFF_F", "0o1_777_777_777_777_777_777_777", binary.as_str()] {
+        let err = lex_to_spanned_tokens(source).expect_err("Overflowing literal lexed");
+        let lexical_error = err.downcast_ref::<LexicalError>().expect("Not a lexer error");
+        assert!(matches!(lexical_error, LexicalError::IntegerOverflow((0, slice, end)) if slice == source && *end == source.len()), "{:?}", lexical_error);
+    }
+}
+
+#[test]
+fn string_escapes_decode_to_their_characters() {
+    let tokens = lex_to_tokens(r#""line1\nline2" "tab\there \"quoted\" back\\slash""#).expect("Failed to lex");
+    let Token::String(lines) = &tokens[0] else { panic!("Not a string: {:?}", tokens[0]) };
+    assert_eq!(lines, "line1\nline2");
+    assert_eq!(lines.len(), 11);
+    assert_eq!(tokens[1], Token::String("tab\there \"quoted\" back\\slash".to_string()));
+}
+
+#[test]
+fn unknown_string_escape_is_an_error() {
+    let err = lex_to_spanned_tokens(r#"let s = "a\qb""#).expect_err("Unknown escape lexed");
+    let lexical_error = err.downcast_ref::<LexicalError>().expect("Not a lexer error");
+    assert!(matches!(lexical_error, LexicalError::InvalidEscape((8, _, 14))), "{:?}", lexical_error);
+}
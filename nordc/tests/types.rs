@@ -0,0 +1,50 @@
+use nordc::mir::{self, WasmOptions};
+use nordc::runtime::Runtime;
+use nordc::{mir_to_wasm_bytes, parse_to_ast};
+
+fn run_formatted(source: &str) -> String {
+    let ast = parse_to_ast(source).expect("Failed to parse");
+    let (bytecode, result_type) = mir::compile_typed(&ast).expect("Failed to compile");
+    let wasm = mir_to_wasm_bytes(&bytecode, WasmOptions::for_program(&ast)).expect("Failed to lower");
+    let result = Runtime::new(&wasm).and_then(|mut runtime| runtime.run(&[])).expect("Failed to run");
+    result_type.format(result)
+}
+
+#[test]
+fn comparisons_display_as_booleans() {
+    assert_eq!(run_formatted("1 == 1"), "true");
+    assert_eq!(run_formatted("let b = 2 < 1; b"), "false");
+    assert_eq!(run_formatted("let x = 3; if x > 2 then true else false end"), "true");
+}
+
+#[test]
+fn numbers_and_unit_display_as_before() {
+    assert_eq!(run_formatted("1 + 1"), "2");
+    assert_eq!(run_formatted("let x = 1"), "()");
+}
+
+#[test]
+fn if_without_else_has_no_value() {
+    let ast = parse_to_ast("1 + (if false then 2 end)").expect("Failed to parse");
+    let err = mir::compile(&ast).expect_err("Missing else used as a value");
+    assert!(err.to_string().contains("`if` without `else` cannot be used as a value"), "{}", err);
+
+    // As a statement the missing value is never looked at
+    assert_eq!(run_formatted("if false then 2 end; 1 + 1"), "2");
+}
+
+#[test]
+fn while_takes_the_type_of_its_body() {
+    assert_eq!(run_formatted("let i = 0; let sum = 0; while i < 5 do i = i + 1; sum = sum + i; sum end"), "15");
+    assert_eq!(run_formatted("let i = 0; while i < 5 do i = i + 1; i == 5 end"), "true");
+    assert_eq!(run_formatted("let i = 0; while i < 5 do i = i + 1 end"), "()");
+    // A body that never runs leaves the zero of its type
+    assert_eq!(run_formatted("let i = 5; while i < 5 do i = i + 1; i == 5 end"), "false");
+    let ast = parse_to_ast("let i = 5; while i < 5 do [i] end").expect("Failed to parse");
+    let (bytecode, result_type) = mir::compile_typed(&ast).expect("Failed to compile");
+    assert_eq!(result_type, mir::ValueType::Array);
+    let wasm = mir_to_wasm_bytes(&bytecode, WasmOptions::for_program(&ast)).expect("Failed to lower");
+    let mut runtime = Runtime::new(&wasm).expect("Failed to instantiate");
+    let address = runtime.run(&[]).expect("Failed to run");
+    assert_eq!(runtime.read_array(address).expect("Failed to read"), Vec::<i64>::new());
+}
@@ -0,0 +1,75 @@
+//! Compiles representative programs and compares the printed module against the snapshots in `tests/snapshots`,
+//! as `.wat` files, and their MIR printed by `to_pseudo_wat` as `.mir` files.
+//!
+//! Run with `UPDATE_SNAPSHOTS=1` to rewrite the snapshots after an intended codegen change or to record a new one,
+//! a missing snapshot fails the test otherwise.
+
+use std::path::PathBuf;
+
+use nordc::mir::{self, WasmOptions};
+use nordc::{compile_to_mir, parse_to_ast};
+
+fn assert_wat_snapshot(name: &str, source: &str) {
+    let ast = parse_to_ast(source).expect("Failed to parse");
+    let bytecode = compile_to_mir(&ast).expect("Failed to compile");
+    let wat = mir::to_wat_text(&bytecode, WasmOptions::for_program(&ast)).expect("Failed to print");
+    assert_snapshot(&format!("{}.wat", name), &wat);
+}
+
+fn assert_pseudo_wat_snapshot(name: &str, source: &str) {
+    let ast = parse_to_ast(source).expect("Failed to parse");
+    let bytecode = compile_to_mir(&ast).expect("Failed to compile");
+    assert_snapshot(&format!("{}.mir", name), &mir::to_pseudo_wat(&bytecode));
+}
+
+fn assert_snapshot(file_name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/snapshots").join(file_name);
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some_and(|value| value != "0");
+    if update {
+        std::fs::create_dir_all(path.parent().unwrap()).expect("Failed to create snapshot directory");
+        std::fs::write(&path, actual).expect("Failed to write snapshot");
+        return;
+    }
+    let expected = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Failed to read snapshot {} ({}), run with UPDATE_SNAPSHOTS=1 to record it", path.display(), err));
+    assert!(
+        expected == actual,
+        "Output differs from {}, rerun with UPDATE_SNAPSHOTS=1 if the change is intended\n--- expected\n{}\n--- actual\n{}",
+        path.display(), expected, actual,
+    );
+}
+
+#[test]
+fn arithmetic() {
+    assert_wat_snapshot("arithmetic", "1 + 2 * 3 - -4 / 2 % 3");
+}
+
+#[test]
+fn if_else() {
+    assert_wat_snapshot("if_else", "let x = 3; if x < 5 then x * 2 else x end");
+}
+
+#[test]
+fn loop_break() {
+    assert_wat_snapshot("loop_break", "let i = 0; loop i = i + 1; if i == 10 then break i end end");
+}
+
+#[test]
+fn while_loop() {
+    assert_wat_snapshot("while_loop", "let i = 0; let sum = 0; while i < 5 do sum = sum + i; i = i + 1 end; sum");
+}
+
+#[test]
+fn function() {
+    assert_wat_snapshot("function", "fn(a) fn(b) a * b + 1");
+}
+
+#[test]
+fn pseudo_wat_if_else() {
+    assert_pseudo_wat_snapshot("if_else", "let x = 3; if x < 5 then x * 2 else x end");
+}
+
+#[test]
+fn pseudo_wat_while_loop() {
+    assert_pseudo_wat_snapshot("while_loop", "let i = 0; let sum = 0; while i < 5 do sum = sum + i; i = i + 1 end; sum");
+}